@@ -66,13 +66,13 @@ fn get_asteroids(region: &Region, from_x: i64, from_y: i64) -> HashMap<(i64, i64
     let mut result = HashMap::new();
     for other_x in 0..region.width {
         for other_y in 0..region.height {
-            let dx = (other_x as i64) - from_x;
-            let dy = (other_y as i64) - from_y;
+            let dx = other_x - from_x;
+            let dy = other_y - from_y;
             if gcd(dx, dy) != 1 {
                 continue;
             }
             let asteroids = get_asteroids_dir(region, from_x, from_y, dx, dy);
-            if asteroids.len() > 0 {
+            if !asteroids.is_empty() {
                 result.insert((dx, dy), asteroids);
             }
         }
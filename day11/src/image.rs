@@ -0,0 +1,72 @@
+use std::error::Error;
+use std::io::Write;
+use std::iter;
+use crate::robot::{Bounds, HullRobot, WHITE};
+
+/// A black and white picture of part of the hull.
+pub struct Image {
+    width: usize,
+    height: usize,
+    /// Row by row, `true` for white.
+    pixels: Vec<bool>
+}
+
+impl Image {
+
+    /// Takes a picture of the panels within `bounds`.
+    pub fn capture(robot: &HullRobot, ((x0, y0), (x1, y1)): Bounds) -> Self {
+        let pixels = (y0..=y1)
+            .flat_map(|y| (x0..=x1).map(move |x| (x, y)))
+            .map(|pos| robot.color(pos) == WHITE)
+            .collect();
+        Self { width: (x1 - x0 + 1) as usize, height: (y1 - y0 + 1) as usize, pixels }
+    }
+
+    pub fn rows(&self) -> impl Iterator<Item = &[bool]> {
+        self.pixels.chunks(self.width)
+    }
+
+    /// The picture as text, with `O` for white and `.` for black.
+    pub fn to_text(&self) -> String {
+        let mut text = String::new();
+        for row in self.rows() {
+            text.extend(row.iter().map(|&white| if white { 'O' } else { '.' }));
+            text.push('\n');
+        }
+        text
+    }
+
+    /// Writes the picture as a plain PBM, with each panel `scale` pixels across.
+    pub fn write_pbm(&self, out: &mut dyn Write, scale: usize) -> Result<(), Box<dyn Error>> {
+        writeln!(out, "P1")?;
+        writeln!(out, "{} {}", self.width * scale, self.height * scale)?;
+        for row in self.rows() {
+            // PBM uses 1 for black
+            let line: Vec<_> = row.iter()
+                .flat_map(|&white| iter::repeat_n(if white { "0" } else { "1" }, scale))
+                .collect();
+            for _ in 0..scale {
+                writeln!(out, "{}", line.join(" "))?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes the picture as a grayscale PNG, with each panel `scale` pixels across.
+    pub fn write_png(&self, out: &mut dyn Write, scale: usize) -> Result<(), Box<dyn Error>> {
+        let mut encoder = png::Encoder::new(out, (self.width * scale) as u32, (self.height * scale) as u32);
+        encoder.set_color(png::ColorType::Grayscale);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut data = Vec::with_capacity(self.pixels.len() * scale * scale);
+        for row in self.rows() {
+            let line: Vec<u8> = row.iter()
+                .flat_map(|&white| iter::repeat_n(if white { 255 } else { 0 }, scale))
+                .collect();
+            for _ in 0..scale {
+                data.extend_from_slice(&line);
+            }
+        }
+        encoder.write_header()?.write_image_data(&data)?;
+        Ok(())
+    }
+}
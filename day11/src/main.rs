@@ -1,203 +1,112 @@
-use std::error::Error;
-use std::io::{self, Read};
-use std::num::ParseIntError;
-use std::collections::{HashMap, VecDeque};
-
-type Word = i64;
-
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
-enum PaintColor {
-    White,
-    Black
-}
-
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
-enum VMState {
-    Ready,
-    Halted,
-    Waiting
-}
-
-#[derive(Debug)]
-struct VM {
-    pc: usize,
-    rb: Word,
-    mem: Vec<Word>,
-    state: VMState,
-    inputs: VecDeque<Word>,
-    outputs: VecDeque<Word>
-}
-
-fn tokenize(input: &str) -> Result<Vec<Word>, ParseIntError> {
-    input.split(',').map(|s| s.parse()).collect()
-}
-
-impl VM {
-
-    pub fn create(program: &str, initial_inputs: &[Word], mem_size_words: usize) -> Result<Self, ParseIntError> {
-        let contents = tokenize(program.trim_end())?;
-        let mut mem = vec![0; mem_size_words];
-        mem[0..contents.len()].copy_from_slice(&contents);
-        let mut inputs = VecDeque::new();
-        inputs.extend(initial_inputs);
-        Ok(Self { mem, inputs, outputs: VecDeque::new(), pc: 0, rb: 0, state: VMState::Ready })
-    }
-
-    fn mode_for(i: u32, mode: u32) -> u32 {
-        mode % 10_u32.pow(i + 1) / 10_u32.pow(i)
-    }
+mod image;
+mod robot;
 
-    fn w_parm(&self, i: u32, modes: u32) -> usize {
-        let p = self.mem[self.pc + (i as usize) + 1];
-        match VM::mode_for(i, modes) {
-            0 => p as usize,
-            1 => panic!("Immediate mode for write parameters is invalid."),
-            2 => (p + self.rb) as usize,
-            _ => panic!(format!("Fishy mode: {}", modes))
-        }
-    }
+use std::env;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, BufWriter, Read, Write};
+use std::thread;
+use std::time::Duration;
+use image::Image;
+use robot::{HullRobot, BLACK, WHITE};
 
-    fn r_parm(&self, i: u32, modes: u32) -> Word {
-        let p = self.mem[self.pc + (i as usize) + 1];
-        match VM::mode_for(i, modes) {
-            0 => self.mem[p as usize],
-            1 => p,
-            2 => self.mem[(p + self.rb) as usize],
-            _ => panic!(format!("Fishy mode: {}", modes))
-        }
-    }
+const USAGE: &str = "\
+Usage: day11 [--start black|white] [--format text|pbm|png] [--scale <n>] [--output <file>]
+             [--animate] [--fps <n>] < program";
 
-    pub fn run(self: &mut Self) {
-        loop {
-            let instr = self.mem[self.pc] as u32;
-            let opcode = instr % 100;
-            let modes = instr / 100;
-            match opcode {
-                1 => { // add
-                    let a = self.r_parm(0, modes);
-                    let b = self.r_parm(1, modes);
-                    let c = self.w_parm(2, modes);
-                    self.mem[c] = a + b;
-                    self.pc += 4;
-                }
-                2 => { // mul
-                    let a = self.r_parm(0, modes);
-                    let b = self.r_parm(1, modes);
-                    let c = self.w_parm(2, modes);
-                    self.mem[c] = a * b;
-                    self.pc += 4;
-                }
-                3 => { // input
-                    if let Some(n) = self.inputs.pop_front() {
-                        let dest = self.w_parm(0, modes);
-                        self.mem[dest] = n;
-                        self.pc += 2;
-                    } else {
-                        self.state = VMState::Waiting;
-                        return;
-                    }
-                }
-                4 => { // output
-                    let a = self.r_parm(0, modes);
-                    self.outputs.push_back(a);
-                    self.pc += 2;
-                }
-                5 => { // jump if true
-                    let a = self.r_parm(0, modes);
-                    let b = self.r_parm(1, modes);
-                    self.pc = if a != 0 { b as usize } else { self.pc + 3 };
-                }
-                6 => { // jump if false
-                    let a = self.r_parm(0, modes);
-                    let b = self.r_parm(1, modes);
-                    self.pc = if a == 0 { b as usize } else { self.pc + 3 };
-                }
-                7 => { // lt
-                    let a = self.r_parm(0, modes);
-                    let b = self.r_parm(1, modes);
-                    let c = self.w_parm(2, modes);
-                    self.mem[c] = if a < b { 1 } else { 0 };
-                    self.pc += 4;
-                }
-                8 => { // eq
-                    let a = self.r_parm(0, modes);
-                    let b = self.r_parm(1, modes);
-                    let c = self.w_parm(2, modes);
-                    self.mem[c] = if a == b { 1 } else { 0 };
-                    self.pc += 4;
-                }
-                9 => { // relative base offset
-                    let a = self.r_parm(0, modes);
-                    self.rb += a;
-                    self.pc += 2;
-                }
-                99 => {
-                    self.state = VMState::Halted;
-                    return;
-                }
-                _ => {
-                    panic!(format!("Fishy opcode found: {}", opcode));
-                }
-            }
-        }
-    }
+enum Format {
+    Text,
+    Pbm,
+    Png
 }
 
-fn rotate_left(direction: (i64, i64)) -> (i64, i64) {
-    match direction {
-        (0, -1) => (-1, 0),
-        (-1, 0) => (0, 1),
-        (0, 1) => (1, 0),
-        (1, 0) => (0, -1),
-        _ => panic!(format!("Invalid direction: {:?}", direction))
+fn robot_char(heading: (i64, i64)) -> char {
+    match heading {
+        (0, -1) => '^',
+        (1, 0) => '>',
+        (0, 1) => 'v',
+        _ => '<'
     }
 }
 
-fn rotate_right(direction: (i64, i64)) -> (i64, i64) {
-    match direction {
-        (0, -1) => (1, 0),
-        (1, 0) => (0, 1),
-        (0, 1) => (-1, 0),
-        (-1, 0) => (0, -1),
-        _ => panic!(format!("Invalid direction: {:?}", direction))
+/// Runs the robot to completion, drawing the hull around it after every step.
+fn animate(robot: &mut HullRobot, frame_time: Duration) -> Result<(), Box<dyn Error>> {
+    loop {
+        let ((x0, y0), (x1, y1)) = robot.bounds();
+        let (x, y) = robot.position;
+        let bounds = ((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y)));
+        let ((x0, y0), (x1, _)) = bounds;
+        let mut text: Vec<char> = Image::capture(robot, bounds).to_text().chars().collect();
+        // each row has a newline at the end
+        text[((y - y0) * (x1 - x0 + 2) + x - x0) as usize] = robot_char(robot.heading);
+        print!("\x1b[2J\x1b[H{}", text.into_iter().collect::<String>());
+        io::stdout().flush()?;
+        if !robot.step()? {
+            return Ok(());
+        }
+        thread::sleep(frame_time);
     }
 }
 
-fn add(location: (i64, i64), direction: (i64, i64)) -> (i64, i64) {
-    let (x, y) = location;
-    let (dx, dy) = direction;
-    (x+dx, y+dy)
-}
-
 fn main() -> Result<(), Box<dyn Error>> {
+    let mut start_color = WHITE;
+    let mut format = Format::Text;
+    let mut scale = 1;
+    let mut output = None;
+    let mut animated = false;
+    let mut fps = 30;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--start" => start_color = match args.next().ok_or(USAGE)?.as_str() {
+                "black" => BLACK,
+                "white" => WHITE,
+                _ => return Err(From::from(USAGE))
+            },
+            "--format" => format = match args.next().ok_or(USAGE)?.as_str() {
+                "text" => Format::Text,
+                "pbm" => Format::Pbm,
+                "png" => Format::Png,
+                _ => return Err(From::from(USAGE))
+            },
+            "--scale" => scale = args.next().ok_or(USAGE)?.parse()?,
+            "--output" => output = Some(args.next().ok_or(USAGE)?),
+            "--animate" => animated = true,
+            "--fps" => fps = args.next().ok_or(USAGE)?.parse()?,
+            _ => return Err(From::from(USAGE))
+        }
+    }
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
-    let mut vm = VM::create(&input, &[], 10000)?;
-    let mut painted = HashMap::new();
-    painted.insert((0, 0), 1);
-    let mut location = (0, 0);
-    let mut vector = (0, -1);
-    while vm.state != VMState::Halted {
-        let color = painted.get(&location).unwrap_or(&0);
-        vm.inputs.push_back(*color);
-        vm.run();
-        let paint_color = vm.outputs.pop_front().expect("No color provided!");
-        let rotation = vm.outputs.pop_front().expect("No rotation provided!");
-        println!("Painting: {:?}, {}", location, paint_color);
-        painted.insert(location, paint_color);
-        vector = if rotation == 0 { rotate_left(vector) } else { rotate_right(vector) };
-        location = add(location, vector);
+    let mut robot = HullRobot::new(&input, start_color)?;
+    if animated {
+        animate(&mut robot, Duration::from_secs(1) / fps.max(1))?;
+    } else {
+        robot.run()?;
     }
-    for row in 0..10 {
-        for col in 0..50 {
-            if *painted.get(&(col, row)).unwrap_or(&0) == 0 {
-                print!(".");
-            } else {
-                print!("O");
-            }
-        }
-        println!();
+    let image = Image::capture(&robot, robot.bounds());
+    let mut out: Box<dyn Write> = match &output {
+        Some(path) => Box::new(BufWriter::new(File::create(path)?)),
+        None => Box::new(io::stdout())
+    };
+    match format {
+        // the last frame of the animation already showed it
+        Format::Text if animated && output.is_none() => {}
+        Format::Text => write!(out, "{}", image.to_text())?,
+        Format::Pbm => image.write_pbm(&mut out, scale.max(1))?,
+        Format::Png => image.write_png(&mut out, scale.max(1))?
+    }
+    out.flush()?;
+    let mut report = format!("Painted tiles: {}\n", robot.painted().len());
+    let rows: Vec<_> = image.rows().collect();
+    if let Some(text) = ocr::recognize(&rows) {
+        report += &format!("Registration identifier: {}\n", text);
+    }
+    // keep stdout clean for the image
+    if output.is_none() && !matches!(format, Format::Text) {
+        eprint!("{}", report);
+    } else {
+        print!("{}", report);
     }
-    println!("Painted tiles: {:?}", painted.keys().count());
     Ok(())
 }
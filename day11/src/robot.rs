@@ -0,0 +1,122 @@
+use std::collections::HashMap;
+use std::error::Error;
+use intcode::{MemoryKind, Vm, VmState, Word};
+
+pub const BLACK: Word = 0;
+pub const WHITE: Word = 1;
+
+pub fn rotate_left(direction: (i64, i64)) -> (i64, i64) {
+    match direction {
+        (0, -1) => (-1, 0),
+        (-1, 0) => (0, 1),
+        (0, 1) => (1, 0),
+        (1, 0) => (0, -1),
+        _ => panic!("Invalid direction: {:?}", direction)
+    }
+}
+
+pub fn rotate_right(direction: (i64, i64)) -> (i64, i64) {
+    match direction {
+        (0, -1) => (1, 0),
+        (1, 0) => (0, 1),
+        (0, 1) => (-1, 0),
+        (-1, 0) => (0, -1),
+        _ => panic!("Invalid direction: {:?}", direction)
+    }
+}
+
+pub fn add(location: (i64, i64), direction: (i64, i64)) -> (i64, i64) {
+    let (x, y) = location;
+    let (dx, dy) = direction;
+    (x+dx, y+dy)
+}
+
+/// The smallest rectangle containing some panels, as inclusive `(min, max)` corners.
+pub type Bounds = ((i64, i64), (i64, i64));
+
+/// The emergency hull painting robot, with the hull it's painting. It starts at `(0, 0)` facing up, with
+/// every panel black except possibly the one it's standing on.
+pub struct HullRobot {
+    vm: Vm,
+    start_color: Word,
+    painted: HashMap<(i64, i64), Word>,
+    pub position: (i64, i64),
+    pub heading: (i64, i64)
+}
+
+impl HullRobot {
+
+    pub fn new(program: &str, start_color: Word) -> Result<Self, Box<dyn Error>> {
+        Ok(Self {
+            vm: Vm::create(program, &[], MemoryKind::Growable)?,
+            start_color,
+            painted: HashMap::new(),
+            position: (0, 0),
+            heading: (0, -1)
+        })
+    }
+
+    pub fn is_halted(&self) -> bool {
+        self.vm.state == VmState::Halted
+    }
+
+    /// The color of a panel, whether or not it's been painted.
+    pub fn color(&self, location: (i64, i64)) -> Word {
+        match self.painted.get(&location) {
+            Some(&color) => color,
+            None if location == (0, 0) => self.start_color,
+            None => BLACK
+        }
+    }
+
+    /// Every panel painted at least once, with its current color.
+    pub fn painted(&self) -> &HashMap<(i64, i64), Word> {
+        &self.painted
+    }
+
+    /// Reads the current panel to the robot, then paints and moves as it says. Returns `false` once the
+    /// robot has halted instead.
+    pub fn step(&mut self) -> Result<bool, Box<dyn Error>> {
+        if self.is_halted() {
+            return Ok(false);
+        }
+        self.vm.inputs.push_back(self.color(self.position));
+        self.vm.run()?;
+        if self.is_halted() && self.vm.outputs.is_empty() {
+            return Ok(false);
+        }
+        let color = self.vm.outputs.pop_front().ok_or("No color provided!")?;
+        let rotation = self.vm.outputs.pop_front().ok_or("No rotation provided!")?;
+        if color != BLACK && color != WHITE {
+            return Err(From::from(format!("Invalid color: {}", color)));
+        }
+        self.painted.insert(self.position, color);
+        self.heading = match rotation {
+            0 => rotate_left(self.heading),
+            1 => rotate_right(self.heading),
+            _ => return Err(From::from(format!("Invalid rotation: {}", rotation)))
+        };
+        self.position = add(self.position, self.heading);
+        Ok(true)
+    }
+
+    /// Steps until the robot halts.
+    pub fn run(&mut self) -> Result<(), Box<dyn Error>> {
+        while self.step()? {}
+        Ok(())
+    }
+
+    /// The bounds of every panel painted so far, along with the starting panel.
+    pub fn bounds(&self) -> Bounds {
+        // there's always the starting panel, so there's always a box
+        bounds(self.painted.keys().copied().chain(Some((0, 0)))).unwrap()
+    }
+}
+
+/// The bounds of some positions, or `None` if there aren't any.
+fn bounds(mut positions: impl Iterator<Item = (i64, i64)>) -> Option<Bounds> {
+    let first = positions.next()?;
+    Some(positions.fold((first, first), |((x0, y0), (x1, y1)), (x, y)| {
+        ((x0.min(x), y0.min(y)), (x1.max(x), y1.max(y)))
+    }))
+}
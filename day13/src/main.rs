@@ -1,212 +1,173 @@
+mod replay;
+mod screen;
+mod strategy;
+
+use std::collections::VecDeque;
+use std::env;
 use std::error::Error;
-use std::io::{self, Read};
-use std::num::ParseIntError;
-use std::cmp::Ordering;
-use std::collections::{VecDeque};
+use std::io::{self, Read, Write};
+use std::thread;
+use std::time::Duration;
+use intcode::{MemoryKind, Snapshot, Vm, VmState, Word};
+use replay::Replay;
+use screen::Screen;
+use strategy::{Follower, Human, Predictor, Strategy};
 
-type Word = i64;
+const USAGE: &str = "\
+Usage: day13 --demo [--render] < program
+       day13 [--strategy follow|predict|human] [--replay <file>] [--record <file>] [--rewind <frames>]
+             [--expect-score <n>] [--render] [--fps <n>] < program";
 
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
-enum VMState {
-    Ready,
-    Halted,
-    Waiting
+/// How the game is being played.
+struct Options {
+    render: bool,
+    frame_time: Duration,
+    /// How many frames back losing the ball can rewind to. 0 turns rewinding off.
+    rewind: usize
 }
 
-#[derive(Debug)]
-struct VM {
-    pc: usize,
-    rb: Word,
-    mem: Vec<Word>,
-    state: VMState,
-    inputs: VecDeque<Word>,
-    outputs: VecDeque<Word>
+/// The state of a game just before a joystick input, kept for rewinding.
+struct Frame {
+    vm: Snapshot,
+    screen: Screen,
+    inputs: usize
 }
 
-fn tokenize(input: &str) -> Result<Vec<Word>, ParseIntError> {
-    input.split(',').map(|s| s.parse()).collect()
+fn draw(screen: &Screen, frame_time: Duration) {
+    // carriage returns too, in case the terminal is in raw mode
+    print!("\x1b[2J\x1b[H{}", screen.render().replace('\n', "\r\n"));
+    let _ = io::stdout().flush();
+    thread::sleep(frame_time);
 }
 
-impl VM {
-
-    pub fn create(program: &str, initial_inputs: &[Word], mem_size_words: usize) -> Result<Self, ParseIntError> {
-        let contents = tokenize(program.trim_end())?;
-        let mut mem = vec![0; mem_size_words];
-        mem[0..contents.len()].copy_from_slice(&contents);
-        let mut inputs = VecDeque::new();
-        inputs.extend(initial_inputs);
-        Ok(Self { mem, inputs, outputs: VecDeque::new(), pc: 0, rb: 0, state: VMState::Ready })
-    }
-
-    fn mode_for(i: usize, mode: Word) -> Word {
-        match i {
-            0 => (mode % 10),
-            1 => (mode % 100) / 10,
-            2 => (mode % 1000) / 100,
-            _ => panic!("Only three parameter modes are supported.")
+/// Plays until the game ends, recording every joystick input in `inputs`. Returns the number of blocks
+/// on the screen when it was first drawn.
+fn play(vm: &mut Vm, screen: &mut Screen, strategy: &mut dyn Strategy, options: &Options, inputs: &mut Vec<Word>)
+        -> Result<usize, Box<dyn Error>> {
+    let mut history: VecDeque<Frame> = VecDeque::new();
+    let mut initial_blocks = None;
+    loop {
+        vm.run()?;
+        screen.apply(&mut vm.outputs)?;
+        if initial_blocks.is_none() {
+            if vm.state == VmState::Halted {
+                return Err(From::from("The game ended before asking for any input; is free play supported?"));
+            }
+            if screen.score.is_none() {
+                return Err(From::from("The game never put up a score display!"));
+            }
+            initial_blocks = Some(screen.blocks());
         }
-    }
-
-    fn parm(&self, i: usize, modes: Word) -> usize {
-        let p = self.pc + i + 1;
-        match VM::mode_for(i, modes) {
-            1 => p,
-            0 => self.mem[p] as usize,
-            2 => (self.mem[p] + self.rb) as usize,
-            _ => panic!(format!("Fishy mode: {}", modes))
+        if options.render {
+            draw(screen, options.frame_time);
         }
-    }
-
-    pub fn run(self: &mut Self) {
-        loop {
-            let instr = self.mem[self.pc];
-            let opcode = instr % 100;
-            let modes = instr / 100;
-            match opcode {
-                1 => { // add
-                    let a = self.parm(0, modes);
-                    let b = self.parm(1, modes);
-                    let c = self.parm(2, modes);
-                    self.mem[c] = self.mem[a] + self.mem[b];
-                    self.pc += 4;
-                }
-                2 => { // mul
-                    let a = self.parm(0, modes);
-                    let b = self.parm(1, modes);
-                    let c = self.parm(2, modes);
-                    self.mem[c] = self.mem[a] * self.mem[b];
-                    self.pc += 4;
-                }
-                3 => { // input
-                    if let Some(n) = self.inputs.pop_front() {
-                        let dest = self.parm(0, modes);
-                        self.mem[dest] = n;
-                        self.pc += 2;
-                    } else {
-                        self.state = VMState::Waiting;
-                        return;
-                    }
-                }
-                4 => { // output
-                    let a = self.parm(0, modes);
-                    self.outputs.push_back(self.mem[a]);
-                    self.pc += 2;
-                }
-                5 => { // jump if true
-                    let a = self.parm(0, modes);
-                    let b = self.parm(1, modes);
-                    self.pc = if self.mem[a] != 0 { self.mem[b] as usize } else { self.pc + 3 };
-                }
-                6 => { // jump if false
-                    let a = self.parm(0, modes);
-                    let b = self.parm(1, modes);
-                    self.pc = if self.mem[a] == 0 { self.mem[b] as usize } else { self.pc + 3 };
-                }
-                7 => { // lt
-                    let a = self.parm(0, modes);
-                    let b = self.parm(1, modes);
-                    let c = self.parm(2, modes);
-                    self.mem[c] = if self.mem[a] < self.mem[b] { 1 } else { 0 };
-                    self.pc += 4;
-                }
-                8 => { // eq
-                    let a = self.parm(0, modes);
-                    let b = self.parm(1, modes);
-                    let c = self.parm(2, modes);
-                    self.mem[c] = if self.mem[a] == self.mem[b] { 1 } else { 0 };
-                    self.pc += 4;
-                }
-                9 => { // relative base offset
-                    let a = self.parm(0, modes);
-                    self.rb += self.mem[a];
-                    self.pc += 2;
-                }
-                99 => {
-                    self.state = VMState::Halted;
-                    return;
-                }
-                _ => {
-                    panic!(format!("Fishy opcode found: {}", opcode));
-                }
+        if vm.state == VmState::Halted {
+            if screen.blocks() > 0 && !history.is_empty() && strategy.retry()? {
+                history.truncate(history.len().saturating_sub(options.rewind) + 1);
+                let frame = history.pop_back().unwrap();
+                vm.restore(&frame.vm);
+                *screen = frame.screen;
+                inputs.truncate(frame.inputs);
+                continue;
             }
+            return Ok(initial_blocks.unwrap_or(0));
         }
-    }
-}
-
-#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
-enum TileKind {
-    Empty = 0,
-    Wall = 1,
-    Block = 2,
-    Paddle = 3,
-    Ball = 4
-}
-
-impl TileKind {
-    fn from_word(i: Word) -> Result<Self, Box<dyn Error>> {
-        match i {
-            0 => Ok(Self::Empty),
-            1 => Ok(Self::Wall),
-            2 => Ok(Self::Block),
-            3 => Ok(Self::Paddle),
-            4 => Ok(Self::Ball),
-            _ => Err(From::from(format!("Invalid tile: {}", i)))
+        if options.rewind > 0 {
+            history.push_back(Frame { vm: vm.snapshot(), screen: screen.clone(), inputs: inputs.len() });
+            if history.len() > options.rewind {
+                history.pop_front();
+            }
         }
+        let dir = strategy.joystick(screen)?;
+        inputs.push(dir);
+        vm.inputs.push_back(dir);
     }
 }
 
-struct GameState {
-    score: Word,
-    paddle: (Word, Word),
-    ball: (Word, Word)
+/// Runs the attract mode that plays without any quarters, which just draws the starting screen.
+fn run_demo(render: bool) -> Result<(), Box<dyn Error>> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    let mut vm = Vm::create(&input, &[], MemoryKind::Growable)?;
+    vm.run()?;
+    if vm.state != VmState::Halted {
+        return Err(From::from("The demo asked for input; did it get free play by mistake?"));
+    }
+    let mut screen = Screen::new();
+    screen.apply(&mut vm.outputs)?;
+    if render {
+        print!("{}", screen.render());
+    }
+    println!("Blocks: {}", screen.blocks());
+    Ok(())
 }
 
-fn apply_updates(state: &mut GameState, outputs: &mut VecDeque<Word>) -> Result<(), Box<dyn Error>> {
-    let mut vals = outputs.drain(..);
-    while let Some(x) = vals.next() {
-        let y = vals.next().ok_or("Outputs must come in triples!")?;
-        let n = vals.next().ok_or("Outputs must come in triples!")?;
-        if x == -1 && y == 0 {
-            state.score = n;
-        } else {
-            // we actually don't give a fuck about anything but the ball and paddle
-            let tile = TileKind::from_word(n)?;
-            if tile == TileKind::Ball {
-                state.ball = (x, y);
-            } else if tile == TileKind::Paddle {
-                state.paddle = (x, y);
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut demo = false;
+    let mut expect_score: Option<Word> = None;
+    let mut render = false;
+    let mut fps = None;
+    let mut rewind = None;
+    let mut record = None;
+    let mut strategy_name = "follow".to_owned();
+    let mut replay_path = None;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--demo" => demo = true,
+            "--expect-score" => expect_score = Some(args.next().ok_or(USAGE)?.parse()?),
+            "--render" => render = true,
+            "--fps" => fps = Some(args.next().ok_or(USAGE)?.parse()?),
+            "--strategy" => strategy_name = args.next().ok_or(USAGE)?,
+            "--replay" => {
+                strategy_name = "replay".to_owned();
+                replay_path = Some(args.next().ok_or(USAGE)?);
             }
+            "--record" => record = Some(args.next().ok_or(USAGE)?),
+            "--rewind" => rewind = Some(args.next().ok_or(USAGE)?.parse()?),
+            _ => return Err(From::from(USAGE))
         }
     }
-    Ok(())
-}
-
-fn determine_direction(state: &GameState) -> Word {
-    let (px, _py) = state.paddle;
-    let (bx, _by) = state.ball;
-    match px.cmp(&bx) {
-        Ordering::Less => 1,
-        Ordering::Equal => 0,
-        Ordering::Greater => -1
+    if demo {
+        return run_demo(render);
     }
-}
-
-fn main() -> Result<(), Box<dyn Error>> {
+    let mut strategy: Box<dyn Strategy> = match strategy_name.as_str() {
+        "follow" => Box::new(Follower),
+        "predict" => Box::new(Predictor::default()),
+        "replay" => Box::new(Replay::load(replay_path.as_deref().ok_or(USAGE)?)?),
+        "human" => {
+            render = true;
+            fps = fps.or(Some(8));
+            rewind = rewind.or(Some(50));
+            Box::new(Human::new()?)
+        }
+        _ => return Err(From::from(USAGE))
+    };
+    let options = Options {
+        render,
+        frame_time: Duration::from_secs(1) / fps.unwrap_or(30).max(1),
+        rewind: rewind.unwrap_or(0)
+    };
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
-    let mut vm = VM::create(&input, &[], 10000)?;
+    let mut vm = Vm::create(&input, &[], MemoryKind::Growable)?;
+    // two quarters is free play
     vm.mem[0] = 2;
-    let mut state = GameState { score: 0, paddle: (0, 0), ball: (0, 0) };
-    loop {
-        vm.run();
-        apply_updates(&mut state, &mut vm.outputs)?;
-        if vm.state == VMState::Halted {
-            break;
-        } else {
-            let dir = determine_direction(&state);
-            vm.inputs.push_back(dir);
-        }
+    let mut screen = Screen::new();
+    let mut inputs = Vec::new();
+    let result = play(&mut vm, &mut screen, strategy.as_mut(), &options, &mut inputs);
+    drop(strategy);
+    // save the recording even if the game was abandoned
+    if let Some(path) = record {
+        replay::save(&path, &inputs)?;
+    }
+    let initial_blocks = result?;
+    println!("Blocks at start: {}", initial_blocks);
+    println!("Blocks left: {}", screen.blocks());
+    let score = screen.score.unwrap_or(0);
+    println!("Game over. Final score: {}", score);
+    match expect_score {
+        Some(expected) if expected != score => Err(From::from(format!("Expected a score of {}!", expected))),
+        _ => Ok(())
     }
-    println!("Game over. Final score: {}", state.score);
-    Ok(())
 }
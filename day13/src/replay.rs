@@ -0,0 +1,43 @@
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufWriter, Write};
+use std::vec;
+use intcode::Word;
+use crate::screen::Screen;
+use crate::strategy::Strategy;
+
+const HEADER: &str = "arcade-replay 1";
+
+/// Writes a game's joystick inputs, one per line, so `Replay` can play them back.
+pub fn save(path: &str, inputs: &[Word]) -> io::Result<()> {
+    let mut out = BufWriter::new(File::create(path)?);
+    writeln!(out, "{}", HEADER)?;
+    for input in inputs {
+        writeln!(out, "{}", input)?;
+    }
+    out.flush()
+}
+
+/// Plays back recorded joystick inputs. The game is deterministic, so this reproduces the recorded game
+/// exactly.
+pub struct Replay {
+    inputs: vec::IntoIter<Word>
+}
+
+impl Replay {
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let text = fs::read_to_string(path)?;
+        let mut lines = text.lines();
+        if lines.next() != Some(HEADER) {
+            return Err(From::from(format!("Not an arcade replay: {}", path)));
+        }
+        let inputs = lines.map(|line| line.parse()).collect::<Result<Vec<Word>, _>>()?;
+        Ok(Self { inputs: inputs.into_iter() })
+    }
+}
+
+impl Strategy for Replay {
+    fn joystick(&mut self, _screen: &Screen) -> Result<Word, Box<dyn Error>> {
+        Ok(self.inputs.next().ok_or("The replay ran out of inputs!")?)
+    }
+}
@@ -0,0 +1,121 @@
+use std::collections::{HashMap, VecDeque};
+use std::error::Error;
+use std::fmt;
+use intcode::Word;
+
+/// Draw instructions that don't make sense.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum ScreenError {
+    /// The outputs ended partway through an `(x, y, tile)` triple; these are the leftovers.
+    IncompleteTriple(Vec<Word>),
+    InvalidTile { x: Word, y: Word, tile: Word }
+}
+
+impl fmt::Display for ScreenError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ScreenError::IncompleteTriple(leftover) => {
+                write!(f, "outputs must come in (x, y, tile) triples, but {} were left over: {:?}", leftover.len(), leftover)
+            }
+            ScreenError::InvalidTile { x, y, tile } => write!(f, "invalid tile {} at ({}, {})", tile, x, y)
+        }
+    }
+}
+
+impl Error for ScreenError {}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum TileKind {
+    Empty = 0,
+    Wall = 1,
+    Block = 2,
+    Paddle = 3,
+    Ball = 4
+}
+
+impl TileKind {
+
+    fn from_word(i: Word) -> Option<Self> {
+        match i {
+            0 => Some(Self::Empty),
+            1 => Some(Self::Wall),
+            2 => Some(Self::Block),
+            3 => Some(Self::Paddle),
+            4 => Some(Self::Ball),
+            _ => None
+        }
+    }
+
+    fn to_char(self) -> char {
+        match self {
+            Self::Empty => ' ',
+            Self::Wall => '#',
+            Self::Block => '=',
+            Self::Paddle => '-',
+            Self::Ball => 'o'
+        }
+    }
+}
+
+/// Everything the arcade cabinet has drawn so far.
+#[derive(Debug, Clone, Default)]
+pub struct Screen {
+    pub tiles: HashMap<(Word, Word), TileKind>,
+    /// `None` until the program draws the score display.
+    pub score: Option<Word>,
+    pub paddle: (Word, Word),
+    pub ball: (Word, Word)
+}
+
+impl Screen {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies the draw instructions in `outputs`, consuming them.
+    pub fn apply(&mut self, outputs: &mut VecDeque<Word>) -> Result<(), ScreenError> {
+        if !outputs.len().is_multiple_of(3) {
+            let leftover = outputs.iter().skip(outputs.len() / 3 * 3).copied().collect();
+            return Err(ScreenError::IncompleteTriple(leftover));
+        }
+        let mut vals = outputs.drain(..);
+        while let (Some(x), Some(y), Some(n)) = (vals.next(), vals.next(), vals.next()) {
+            if x == -1 && y == 0 {
+                self.score = Some(n);
+            } else {
+                let tile = TileKind::from_word(n).ok_or(ScreenError::InvalidTile { x, y, tile: n })?;
+                if tile == TileKind::Ball {
+                    self.ball = (x, y);
+                } else if tile == TileKind::Paddle {
+                    self.paddle = (x, y);
+                }
+                self.tiles.insert((x, y), tile);
+            }
+        }
+        Ok(())
+    }
+
+    /// How many blocks are left to break.
+    pub fn blocks(&self) -> usize {
+        self.tiles.values().filter(|&&tile| tile == TileKind::Block).count()
+    }
+
+    /// Draws the screen as text, with the score underneath.
+    pub fn render(&self) -> String {
+        let width = self.tiles.keys().map(|&(x, _)| x + 1).max().unwrap_or(0);
+        let height = self.tiles.keys().map(|&(_, y)| y + 1).max().unwrap_or(0);
+        let mut text = String::new();
+        for y in 0..height {
+            for x in 0..width {
+                text.push(self.tiles.get(&(x, y)).map_or(' ', |tile| tile.to_char()));
+            }
+            text.push('\n');
+        }
+        match self.score {
+            Some(score) => text += &format!("Score: {}\n", score),
+            None => text += "Score: -\n"
+        }
+        text
+    }
+}
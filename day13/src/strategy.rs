@@ -0,0 +1,141 @@
+use std::cmp::Ordering;
+use std::error::Error;
+use std::fs::File;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver};
+use std::thread;
+use termion::event::Key;
+use termion::input::TermRead;
+use termion::raw::{IntoRawMode, RawTerminal};
+use intcode::Word;
+use crate::screen::{Screen, TileKind};
+
+/// Decides how to move the joystick.
+pub trait Strategy {
+    /// The joystick position for the next frame: -1 for left, 0 for neutral, 1 for right.
+    fn joystick(&mut self, screen: &Screen) -> Result<Word, Box<dyn Error>>;
+
+    /// Called when the ball is lost with blocks still left. Returns whether to rewind and try again.
+    fn retry(&mut self) -> Result<bool, Box<dyn Error>> {
+        Ok(false)
+    }
+}
+
+fn towards(from: Word, to: Word) -> Word {
+    match from.cmp(&to) {
+        Ordering::Less => 1,
+        Ordering::Equal => 0,
+        Ordering::Greater => -1
+    }
+}
+
+/// Keeps the paddle under the ball.
+pub struct Follower;
+
+impl Strategy for Follower {
+    fn joystick(&mut self, screen: &Screen) -> Result<Word, Box<dyn Error>> {
+        Ok(towards(screen.paddle.0, screen.ball.0))
+    }
+}
+
+/// Works out where the ball will come down from its last two positions, and heads there.
+#[derive(Default)]
+pub struct Predictor {
+    last_ball: Option<(Word, Word)>
+}
+
+/// Simulates the ball bouncing off walls and blocks (breaking them as it goes), returning the column it's
+/// in when it reaches the row above the paddle on its way down.
+fn predict(screen: &Screen, (mut vx, mut vy): (Word, Word)) -> Option<Word> {
+    if vx == 0 || vy == 0 {
+        return None;
+    }
+    let mut tiles = screen.tiles.clone();
+    let (mut x, mut y) = screen.ball;
+    let row = screen.paddle.1 - 1;
+    let mut hit = |pos: (Word, Word)| match tiles.get(&pos) {
+        Some(TileKind::Wall) => true,
+        Some(TileKind::Block) => {
+            tiles.insert(pos, TileKind::Empty);
+            true
+        }
+        _ => false
+    };
+    for _ in 0..100_000 {
+        if y == row && vy > 0 {
+            return Some(x);
+        }
+        let bounce_x = hit((x + vx, y));
+        let bounce_y = hit((x, y + vy));
+        if bounce_x || bounce_y {
+            if bounce_x { vx = -vx; }
+            if bounce_y { vy = -vy; }
+        } else if hit((x + vx, y + vy)) {
+            vx = -vx;
+            vy = -vy;
+        } else {
+            x += vx;
+            y += vy;
+        }
+    }
+    None
+}
+
+impl Strategy for Predictor {
+    fn joystick(&mut self, screen: &Screen) -> Result<Word, Box<dyn Error>> {
+        let (bx, by) = screen.ball;
+        let velocity = self.last_ball.map_or((0, 0), |(x, y)| (bx - x, by - y));
+        self.last_ball = Some(screen.ball);
+        Ok(towards(screen.paddle.0, predict(screen, velocity).unwrap_or(bx)))
+    }
+}
+
+/// Reads the arrow keys (or `a` and `d`) from the terminal. `q` or escape gives up, and `r` rewinds after
+/// losing the ball.
+pub struct Human {
+    keys: Receiver<Key>,
+    _raw: RawTerminal<File>
+}
+
+impl Human {
+    pub fn new() -> io::Result<Self> {
+        let raw = termion::get_tty()?.into_raw_mode()?;
+        let tty = termion::get_tty()?;
+        let (tx, keys) = mpsc::channel();
+        thread::spawn(move || {
+            for key in tty.keys().flatten() {
+                if tx.send(key).is_err() {
+                    break;
+                }
+            }
+        });
+        Ok(Self { keys, _raw: raw })
+    }
+}
+
+impl Strategy for Human {
+    fn joystick(&mut self, _screen: &Screen) -> Result<Word, Box<dyn Error>> {
+        let mut dir = 0;
+        for key in self.keys.try_iter() {
+            match key {
+                Key::Left | Key::Char('a') => dir = -1,
+                Key::Right | Key::Char('d') => dir = 1,
+                Key::Char('q') | Key::Esc | Key::Ctrl('c') => return Err(From::from("Quit!")),
+                _ => {}
+            }
+        }
+        Ok(dir)
+    }
+
+    fn retry(&mut self) -> Result<bool, Box<dyn Error>> {
+        print!("Ball lost! Press r to rewind, or q to give up.\r\n");
+        io::stdout().flush()?;
+        loop {
+            match self.keys.recv()? {
+                Key::Char('r') => return Ok(true),
+                Key::Char('q') | Key::Esc | Key::Ctrl('c') => return Ok(false),
+                _ => {}
+            }
+        }
+    }
+}
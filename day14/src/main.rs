@@ -7,14 +7,14 @@ type Reagent<'a> = (usize, &'a str);
 
 type Cookbook<'a> = HashMap<&'a str, (usize, Vec<Reagent<'a>>)>;
 
-fn parse_pair(input: &str) -> Result<Reagent, Box<dyn Error>> {
+fn parse_pair(input: &str) -> Result<Reagent<'_>, Box<dyn Error>> {
     let mut parts = input.split_whitespace();
     let qty = parts.next().ok_or("No quantity specified!")?.parse()?;
     let chemical = parts.next().ok_or("No chemical specified!")?;
     Ok((qty, chemical))
 }
 
-fn parse_reactions(input: &str) -> Result<Cookbook, Box<dyn Error>> {
+fn parse_reactions(input: &str) -> Result<Cookbook<'_>, Box<dyn Error>> {
     let mut reactions = HashMap::new();
     for line in input.trim_end().split('\n') {
         let mut sides = line.split(" => ");
@@ -33,7 +33,7 @@ fn required_for<'a>(book: &'a Cookbook, output: &'a str, output_qty: usize) -> H
     let mut desired = HashMap::new();
     let mut present = HashMap::new();
     desired.insert(output, output_qty);
-    while let Some((ch, (qty, inputs))) = book.iter().filter(|(&ch, _)| desired.contains_key(ch)).next() {
+    while let Some((ch, (qty, inputs))) = book.iter().find(|(&ch, _)| desired.contains_key(ch)) {
         let desired_qty = desired.remove(ch).unwrap();
         let batches = (desired_qty - 1) / qty + 1;
         for (input_qty, input_ch) in inputs.iter() {
@@ -41,7 +41,7 @@ fn required_for<'a>(book: &'a Cookbook, output: &'a str, output_qty: usize) -> H
             let required_qty = input_qty * batches;
             match (*already_have).cmp(&required_qty) {
                 Ordering::Less => { *desired.entry(input_ch).or_insert(0) += required_qty - *already_have; }
-                Ordering::Greater => { *already_have = *already_have - required_qty; }
+                Ordering::Greater => { *already_have -= required_qty; }
                 _ => {}
             }
         }
@@ -61,7 +61,7 @@ fn main() -> Result<(), Box<dyn Error>> {
         println!("Required for {} fuel: {} ore", req, hi);
         if req < target {
             lo = hi;
-            hi = hi * 2;
+            hi *= 2;
         } else {
             break;
         }
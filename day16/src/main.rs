@@ -17,9 +17,9 @@ fn multiply_signal(signal: &[i32], times: usize) -> Vec<i32> {
 fn compute_from_offset(signal: &mut [i32], offset: usize) {
     let suffix = &mut signal[offset..];
     let mut sum: i32 = suffix.iter().sum();
-    for i in 0..suffix.len() {
-        let curr = suffix[i];
-        suffix[i] = (sum % 10).abs();
+    for digit in suffix.iter_mut() {
+        let curr = *digit;
+        *digit = (sum % 10).abs();
         sum -= curr;
     }
 }
@@ -27,7 +27,7 @@ fn compute_from_offset(signal: &mut [i32], offset: usize) {
 fn main() -> Result<(), Box<dyn Error>> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
-    let signal = parse_signal(&input.trim_end())?;
+    let signal = parse_signal(input.trim_end())?;
     let offset = input[0..7].parse()?;
     println!("Offset: {}", offset);
     let mut curr = multiply_signal(&signal, 10000);
@@ -0,0 +1,89 @@
+/// How many characters (not counting the newline) the robot can store for each routine.
+pub const MEMORY_LIMIT: usize = 20;
+
+pub const NAMES: [&str; 3] = ["A", "B", "C"];
+
+/// A movement program for the vacuum robot: a main routine calling three movement functions.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Routines {
+    pub main: String,
+    pub functions: [String; 3]
+}
+
+fn fits(parts: &[impl AsRef<str>]) -> bool {
+    parts.iter().map(|p| p.as_ref().len() + 1).sum::<usize>() <= MEMORY_LIMIT + 1
+}
+
+/// Tries to cover `path[pos..]` using the functions so far, plus new ones while there's room for them.
+fn cover<'a>(path: &'a [String], pos: usize, functions: &mut Vec<&'a [String]>, main: &mut Vec<&'static str>) -> bool {
+    if !fits(main) {
+        return false;
+    }
+    if pos == path.len() {
+        return true;
+    }
+    for i in 0..functions.len() {
+        if path[pos..].starts_with(functions[i]) {
+            main.push(NAMES[i]);
+            if cover(path, pos + functions[i].len(), functions, main) {
+                return true;
+            }
+            main.pop();
+        }
+    }
+    if functions.len() < NAMES.len() {
+        for end in pos + 1..=path.len() {
+            if !fits(&path[pos..end]) {
+                break;
+            }
+            main.push(NAMES[functions.len()]);
+            functions.push(&path[pos..end]);
+            if cover(path, end, functions, main) {
+                return true;
+            }
+            functions.pop();
+            main.pop();
+        }
+    }
+    false
+}
+
+/// Splits a path (as produced by `Grid::path`) into a main routine and three functions that all fit in
+/// the robot's memory, if that's possible. An empty path can't be, since the main routine has to call
+/// something.
+pub fn compress(path: &[String]) -> Option<Routines> {
+    if path.is_empty() {
+        return None;
+    }
+    let mut functions = Vec::new();
+    let mut main = Vec::new();
+    if !cover(path, 0, &mut functions, &mut main) {
+        return None;
+    }
+    // functions the main routine never calls still have to be given
+    let text = |i: usize| functions.get(i).or_else(|| functions.first()).map_or("L".to_owned(), |f| f.join(","));
+    Some(Routines { main: main.join(","), functions: [text(0), text(1), text(2)] })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_path_does_not_compress() {
+        assert_eq!(compress(&[]), None);
+    }
+
+    #[test]
+    fn compressed_routines_expand_to_the_path() {
+        let path: Vec<String> = ["R,8", "R,8", "R,4", "R,4", "R,8", "L,6", "L,2", "R,4", "R,4", "R,8", "R,8", "R,8",
+                                 "L,6", "L,2"].iter().map(|s| s.to_string()).collect();
+        let routines = compress(&path).unwrap();
+        let expanded: Vec<&str> = routines.main.split(',')
+            .map(|name| &routines.functions[NAMES.iter().position(|n| *n == name).unwrap()][..])
+            .collect();
+        assert_eq!(expanded.join(","), path.join(","));
+        assert!(routines.main.len() <= MEMORY_LIMIT);
+        assert!(routines.functions.iter().all(|f| f.len() <= MEMORY_LIMIT));
+    }
+}
@@ -0,0 +1,176 @@
+use std::error::Error;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Direction {
+    Up,
+    Down,
+    Left,
+    Right
+}
+
+impl Direction {
+
+    pub fn delta(self) -> (i64, i64) {
+        match self {
+            Direction::Up => (0, -1),
+            Direction::Down => (0, 1),
+            Direction::Left => (-1, 0),
+            Direction::Right => (1, 0)
+        }
+    }
+
+    pub fn left(self) -> Self {
+        match self {
+            Direction::Up => Direction::Left,
+            Direction::Left => Direction::Down,
+            Direction::Down => Direction::Right,
+            Direction::Right => Direction::Up
+        }
+    }
+
+    pub fn right(self) -> Self {
+        self.left().left().left()
+    }
+}
+
+fn step((x, y): (i64, i64), dir: Direction) -> (i64, i64) {
+    let (dx, dy) = dir.delta();
+    (x + dx, y + dy)
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Cell {
+    Open,
+    Scaffold,
+    /// The robot, standing on scaffold.
+    Robot(Direction),
+    /// The robot, tumbling through space.
+    Tumbling
+}
+
+impl Cell {
+    fn from_char(c: char) -> Result<Self, Box<dyn Error>> {
+        match c {
+            '.' => Ok(Cell::Open),
+            '#' => Ok(Cell::Scaffold),
+            '^' => Ok(Cell::Robot(Direction::Up)),
+            'v' => Ok(Cell::Robot(Direction::Down)),
+            '<' => Ok(Cell::Robot(Direction::Left)),
+            '>' => Ok(Cell::Robot(Direction::Right)),
+            'X' => Ok(Cell::Tumbling),
+            _ => Err(From::from(format!("Invalid camera pixel: {:?}", c)))
+        }
+    }
+}
+
+/// What the ASCII camera sees.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Grid {
+    rows: Vec<Vec<Cell>>
+}
+
+impl Grid {
+
+    /// Parses a camera image. The image ends at the first blank line; anything after it (like the
+    /// movement prompt) is ignored.
+    pub fn parse(text: &str) -> Result<Self, Box<dyn Error>> {
+        let rows = text.lines()
+            .take_while(|line| !line.is_empty())
+            .map(|line| line.chars().map(Cell::from_char).collect())
+            .collect::<Result<Vec<Vec<_>>, _>>()?;
+        Ok(Self { rows })
+    }
+
+    pub fn height(&self) -> usize {
+        self.rows.len()
+    }
+
+    pub fn width(&self) -> usize {
+        self.rows.iter().map(|row| row.len()).max().unwrap_or(0)
+    }
+
+    /// The cell at the given position, or `None` if it's outside the image.
+    pub fn get(&self, (x, y): (i64, i64)) -> Option<Cell> {
+        if x < 0 || y < 0 {
+            return None;
+        }
+        self.rows.get(y as usize)?.get(x as usize).copied()
+    }
+
+    pub fn is_scaffold(&self, pos: (i64, i64)) -> bool {
+        matches!(self.get(pos), Some(Cell::Scaffold) | Some(Cell::Robot(_)))
+    }
+
+    /// Where the robot is and which way it's facing, if it's on the scaffold.
+    pub fn robot(&self) -> Option<((i64, i64), Direction)> {
+        self.positions().find_map(|pos| match self.get(pos) {
+            Some(Cell::Robot(dir)) => Some((pos, dir)),
+            _ => None
+        })
+    }
+
+    fn positions(&self) -> impl Iterator<Item = (i64, i64)> + '_ {
+        self.rows.iter().enumerate()
+            .flat_map(|(y, row)| (0..row.len()).map(move |x| (x as i64, y as i64)))
+    }
+
+    /// Scaffold positions with scaffold on all four sides.
+    pub fn intersections(&self) -> Vec<(i64, i64)> {
+        let dirs = [Direction::Up, Direction::Down, Direction::Left, Direction::Right];
+        self.positions()
+            .filter(|&pos| self.is_scaffold(pos))
+            .filter(|&pos| dirs.iter().all(|&d| self.is_scaffold(step(pos, d))))
+            .collect()
+    }
+
+    /// The sum of `x * y` over all intersections.
+    pub fn alignment_sum(&self) -> i64 {
+        self.intersections().iter().map(|(x, y)| x * y).sum()
+    }
+
+    /// The moves that take the robot from where it stands to the far end of the scaffold, going straight
+    /// through intersections, as a list like `["L,4", "R,10", ...]`. Each entry is a turn followed by a
+    /// number of steps forward, except that the first has no turn if the robot starts out facing the
+    /// right way, and turns around (`R,R`) if the scaffold leads off behind it.
+    pub fn path(&self) -> Vec<String> {
+        let mut moves = Vec::new();
+        let (mut pos, mut dir) = match self.robot() {
+            Some(robot) => robot,
+            None => return moves
+        };
+        loop {
+            let turn = if self.is_scaffold(step(pos, dir)) {
+                ""
+            } else if self.is_scaffold(step(pos, dir.left())) {
+                dir = dir.left();
+                "L,"
+            } else if self.is_scaffold(step(pos, dir.right())) {
+                dir = dir.right();
+                "R,"
+            } else if moves.is_empty() && self.is_scaffold(step(pos, dir.right().right())) {
+                // only at the start; after that, what's behind the robot is where it came from
+                dir = dir.right().right();
+                "R,R,"
+            } else {
+                return moves;
+            };
+            let mut steps = 0;
+            while self.is_scaffold(step(pos, dir)) {
+                pos = step(pos, dir);
+                steps += 1;
+            }
+            moves.push(format!("{}{}", turn, steps));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn path_turns_around_at_the_start() {
+        let grid = Grid::parse("..^..\n..#..\n..###\n").unwrap();
+        assert_eq!(grid.path(), ["R,R,2", "L,2"]);
+    }
+}
@@ -1,64 +1,66 @@
+use std::env;
 use std::error::Error;
 use std::io::{self, Read};
-use std::num::ParseIntError;
+use std::ops::Range;
+use std::thread;
+use intcode::{tokenize, InputSearch, MemoryKind, Vm, VmState, Word};
 
-fn parse(input: &str) -> Result<Vec<usize>, ParseIntError> {
-    input.split(',').map(|s| s.parse()).collect()
-}
+const USAGE: &str = "Usage: day2 [--symbolic] [--nouns <from>..<to>] [--verbs <from>..<to>] < program";
 
-fn exec(memory: &mut [usize], opcode: usize, pc: &mut usize) {
-    return match opcode {
-        1 => { // add
-            let noun = memory[*pc+1];
-            let verb = memory[*pc+2];
-            let dest = memory[*pc+3];
-            memory[dest] = memory[noun] + memory[verb];
-            *pc += 4;
-        }
-        2 => { // mul
-            let noun = memory[*pc+1];
-            let verb = memory[*pc+2];
-            let dest = memory[*pc+3];
-            memory[dest] = memory[noun] * memory[verb];
-            *pc += 4;
-        }
-        _ => {
-            panic!(format!("Fishy opcode found: {}", opcode));
+fn search_until<F>(program: &[Word], nouns: Range<Word>, verbs: Range<Word>, predicate: F)
+                   -> Result<Option<(Word, Word)>, Box<dyn Error>> where F: Fn(&Vm) -> bool {
+    let mut vm = Vm::from_words(program, &[], MemoryKind::Growable);
+    let initial = vm.snapshot();
+    for noun in nouns {
+        for verb in verbs.clone() {
+            vm.restore(&initial);
+            vm.mem[1] = noun;
+            vm.mem[2] = verb;
+            vm.run().map_err(|e| format!("Noun {}, verb {}: {}", noun, verb, e))?;
+            if vm.state != VmState::Halted {
+                return Err(From::from(format!("Noun {}, verb {}: program stopped without halting!", noun, verb)));
+            }
+            if predicate(&vm) {
+                return Ok(Some((noun, verb)))
+            }
         }
     }
+    Ok(None)
 }
 
-fn run(memory: &mut [usize]) {
-    let mut pc = 0;
-    loop {
-        match memory[pc] {
-            99 => { return; } // halt
-            op => exec(memory, op, &mut pc)
-        }
-    }
+fn parse_range(s: &str) -> Result<Range<Word>, Box<dyn Error>> {
+    let mut bounds = s.splitn(2, "..");
+    let start = bounds.next().unwrap().parse()?;
+    let end = bounds.next().ok_or("Ranges look like 0..100!")?.parse()?;
+    Ok(start..end)
 }
 
-fn search_until<F>(memory: &[usize], predicate: F) -> (usize, usize) where F: Fn(&[usize]) -> bool {
-    let mut state = memory.to_vec();
-    for noun in 0.. {
-        for verb in 0..noun {
-            state[1] = noun;
-            state[2] = verb;
-            run(&mut state);
-            if predicate(&state) {
-                return (noun, verb)
-            } else {
-                state.copy_from_slice(memory);
-            }
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut symbolic = false;
+    let mut nouns = 0..100;
+    let mut verbs = 0..100;
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--symbolic" => symbolic = true,
+            "--nouns" => nouns = parse_range(&args.next().ok_or(USAGE)?)?,
+            "--verbs" => verbs = parse_range(&args.next().ok_or(USAGE)?)?,
+            _ => return Err(From::from(USAGE))
         }
     }
-    unreachable!()
-}
-
-fn main() -> Result<(), Box<dyn Error>> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
-    let memory = parse(input.trim_end())?;
-    println!("Result: {:?}", search_until(&memory, |s| s[0] == 19690720));
+    let program = tokenize(input.trim_end())?;
+    let (noun, verb) = if symbolic {
+        let search = InputSearch { program: &program, inputs: vec![(1, nouns), (2, verbs)], target: 0, value: 19690720 };
+        let threads = thread::available_parallelism().map_or(1, |n| n.get());
+        let solution = search.solve(threads)?;
+        let values = solution.values.ok_or("No solution!")?;
+        println!("Solved by: {:?}", solution.method);
+        (values[0], values[1])
+    } else {
+        search_until(&program, nouns, verbs, |vm| vm.mem[0] == 19690720)?.ok_or("No solution!")?
+    };
+    println!("Result: {:?}", (noun, verb));
     Ok(())
 }
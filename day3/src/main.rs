@@ -15,11 +15,11 @@ impl<'a> Span<'a> {
     }
 }
 
-fn parse_input(input: &str) -> Result<Vec<Vec<Span>>, Box<dyn Error>> {
+fn parse_input(input: &str) -> Result<Vec<Vec<Span<'_>>>, Box<dyn Error>> {
     input.split_whitespace().map(parse_wire).collect()
 }
 
-fn parse_wire(wire: &str) -> Result<Vec<Span>, Box<dyn Error>> {
+fn parse_wire(wire: &str) -> Result<Vec<Span<'_>>, Box<dyn Error>> {
     wire.split(',').map(Span::from_str).collect()
 }
 
@@ -48,19 +48,19 @@ fn main() -> Result<(), Box<dyn Error>> {
     let mut input = String::new();
     io::stdin().read_to_string(&mut input)?;
     let wires = parse_input(input.trim_end())?;
-    let left_wire = wires.get(0).ok_or("No left wire in input!")?;
+    let left_wire = wires.first().ok_or("No left wire in input!")?;
     let right_wire = wires.get(1).ok_or("No right wire in input!")?;
     let left_locations = get_locations(left_wire)?;
     let right_locations = get_locations(right_wire)?;
 
     let mut min_point: Option<(i32, i32)> = None;
-    let mut min_steps = u64::max_value();
+    let mut min_steps = u64::MAX;
     for (left_point, left_steps) in left_locations.iter() {
         if let Some(right_steps) = right_locations.get(left_point) {
             let total_steps = left_steps + right_steps;
             if total_steps < min_steps {
                 min_steps = total_steps;
-                min_point = Some(left_point.clone());
+                min_point = Some(*left_point);
             }
         }
     }
@@ -11,7 +11,7 @@ fn is_valid_adjacency(pw: &str) -> bool {
             return true;
         }
     }
-    return false;
+    false
 }
 
 fn is_non_decreasing(pw: &str) -> bool {
@@ -22,7 +22,7 @@ fn is_non_decreasing(pw: &str) -> bool {
         }
         prev = ch;
     }
-    return true;
+    true
 }
 
 fn main() {
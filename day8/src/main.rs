@@ -8,7 +8,9 @@ fn main() -> Result<(), Box<dyn Error>> {
     let height = 6;
     let chars: Vec<_> = input.trim_end().chars().collect();
     let layers: Vec<_> = chars.chunks(width*height).collect();
+    let mut pixels = Vec::new();
     for i in 0..height {
+        let mut row = Vec::new();
         for j in 0..width {
             let mut color = '2';
             for layer in &layers {
@@ -17,8 +19,11 @@ fn main() -> Result<(), Box<dyn Error>> {
                 }
             }
             print!("{}", color);
+            row.push(color == '1');
         }
         println!();
+        pixels.push(row);
     }
+    println!("Message: {}", ocr::recognize(&pixels).ok_or("Couldn't find a message in the image!")?);
     Ok(())
 }
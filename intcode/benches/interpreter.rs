@@ -0,0 +1,86 @@
+//! Compares the interpreter with and without the decoded instruction cache on a few puzzle workloads.
+//!
+//! Run with `cargo bench -p intcode`.
+use std::time::{Duration, Instant};
+use intcode::{tokenize, MemoryKind, Network, Vm, Word};
+
+const ROUNDS: u32 = 20;
+
+type Workload = fn(&[Word], bool) -> Word;
+
+/// Day 2: every noun/verb pair, restoring the VM from a snapshot each time.
+fn day2(program: &[Word], cached: bool) -> Word {
+    let mut vm = Vm::from_words(program, &[], MemoryKind::Growable);
+    vm.set_decode_cache(cached);
+    let initial = vm.snapshot();
+    let mut sum = 0;
+    for noun in 0..100 {
+        for verb in 0..100 {
+            vm.restore(&initial);
+            vm.mem[1] = noun;
+            vm.mem[2] = verb;
+            vm.run().unwrap();
+            sum += vm.mem[0];
+        }
+    }
+    sum
+}
+
+/// Day 7: the five amplifier feedback loop, once per permutation's worth of runs.
+fn day7(program: &[Word], cached: bool) -> Word {
+    let mut sum = 0;
+    for _ in 0..120 {
+        let mut net = Network::new();
+        for &p in &[9, 8, 7, 6, 5] {
+            let mut vm = Vm::from_words(program, &[p], MemoryKind::Growable);
+            vm.set_decode_cache(cached);
+            net.add(vm);
+        }
+        for i in 0..5 {
+            net.connect(i, (i + 1) % 5);
+        }
+        net.vm_mut(0).inputs.push_back(0);
+        net.run().unwrap();
+        sum += net.last_output(4).unwrap();
+    }
+    sum
+}
+
+/// Day 9: the sensor boost run.
+fn day9(program: &[Word], cached: bool) -> Word {
+    let mut vm = Vm::from_words(program, &[2], MemoryKind::Growable);
+    vm.set_decode_cache(cached);
+    vm.run().unwrap();
+    vm.outputs[0]
+}
+
+/// The best time of `ROUNDS` runs of `run` without and with the cache, alternating between the two so
+/// that the machine getting faster or slower partway through doesn't favor either.
+fn time(name: &str, run: Workload, program: &[Word]) -> (Duration, Duration) {
+    let mut best = [Duration::MAX; 2];
+    let mut results = [0; 2];
+    for _ in 0..ROUNDS {
+        for (i, &cached) in [false, true].iter().enumerate() {
+            let start = Instant::now();
+            results[i] = run(program, cached);
+            best[i] = best[i].min(start.elapsed());
+        }
+    }
+    assert_eq!(results[0], results[1], "{} gave different results with and without the cache", name);
+    (best[0], best[1])
+}
+
+fn main() {
+    let workloads: [(&str, &str, Workload); 3] = [
+        ("day2", include_str!("../../day2/input"), day2),
+        ("day7", include_str!("../../day7/input"), day7),
+        ("day9", include_str!("../../day9/input"), day9)
+    ];
+    println!("{:<6} {:>12} {:>12} {:>8}", "", "uncached", "cached", "speedup");
+    for (name, input, run) in workloads.iter() {
+        let program = tokenize(input.trim_end()).unwrap();
+        let (uncached, cached) = time(name, *run, &program);
+        println!("{:<6} {:>10.2?} {:>10.2?} {:>7.2}x", name, uncached, cached,
+                 uncached.as_secs_f64() / cached.as_secs_f64());
+    }
+}
@@ -0,0 +1,121 @@
+use std::thread;
+use crate::memory::MemoryKind;
+use crate::network::{Network, NetworkError};
+use crate::vm::Vm;
+use crate::Word;
+
+/// How a chain of amplifiers is wired.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Topology {
+    /// Each stage feeds the next; the last stage's output is the thrust.
+    Serial,
+    /// Like `Serial`, but the last stage also feeds the first, and the thrust is the last stage's final
+    /// output.
+    Feedback
+}
+
+/// A chain of amplifiers all running the same program, each given a distinct phase setting.
+#[derive(Debug, Clone)]
+pub struct Amplifiers<'a> {
+    pub program: &'a [Word],
+    pub stages: usize,
+    /// The phase settings to choose from. Every stage gets a different one.
+    pub phases: Vec<Word>,
+    pub topology: Topology
+}
+
+/// The best phase settings found by a search, and the thrust they produce.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Best {
+    pub settings: Vec<Word>,
+    pub thrust: Word
+}
+
+/// All ordered selections of `k` distinct elements of `items`, in lexicographic order of position.
+fn permutations(items: &[Word], k: usize) -> Vec<Vec<Word>> {
+    fn extend(items: &[Word], used: &mut [bool], current: &mut Vec<Word>, k: usize, out: &mut Vec<Vec<Word>>) {
+        if current.len() == k {
+            out.push(current.clone());
+            return;
+        }
+        for i in 0..items.len() {
+            if !used[i] {
+                used[i] = true;
+                current.push(items[i]);
+                extend(items, used, current, k, out);
+                current.pop();
+                used[i] = false;
+            }
+        }
+    }
+    let mut out = Vec::new();
+    if k <= items.len() {
+        extend(items, &mut vec![false; items.len()], &mut Vec::with_capacity(k), k, &mut out);
+    }
+    out
+}
+
+impl Amplifiers<'_> {
+
+    /// Runs the chain with the given phase settings, one per stage, and returns the thrust, or `None` if
+    /// the last stage never output anything.
+    pub fn thrust(&self, settings: &[Word]) -> Result<Option<Word>, NetworkError> {
+        if settings.is_empty() {
+            return Ok(None);
+        }
+        let mut net = Network::new();
+        for &p in settings {
+            net.add(Vm::from_words(self.program, &[p], MemoryKind::Growable));
+        }
+        for i in 1..net.len() {
+            net.connect(i - 1, i);
+        }
+        let last = net.len() - 1;
+        if self.topology == Topology::Feedback {
+            net.connect(last, 0);
+        }
+        net.vm_mut(0).inputs.push_back(0);
+        net.run()?;
+        Ok(net.last_output(last))
+    }
+
+    /// Tries every assignment of phases to stages, returning the one with the highest thrust (the first
+    /// one, if several tie). Returns `None` if there are no possible assignments.
+    pub fn optimize(&self) -> Result<Option<Best>, NetworkError> {
+        self.optimize_parallel(1)
+    }
+
+    /// Like `optimize`, but splits the candidates across `threads` threads.
+    pub fn optimize_parallel(&self, threads: usize) -> Result<Option<Best>, NetworkError> {
+        let candidates = permutations(&self.phases, self.stages);
+        if candidates.is_empty() {
+            return Ok(None);
+        }
+        let chunk_size = candidates.len().div_ceil(threads.max(1));
+        let results: Vec<Result<Option<(usize, Word)>, NetworkError>> = thread::scope(|s| {
+            let handles: Vec<_> = candidates.chunks(chunk_size).enumerate().map(|(chunk, settings)| {
+                s.spawn(move || {
+                    let mut best: Option<(usize, Word)> = None;
+                    for (i, settings) in settings.iter().enumerate() {
+                        if let Some(thrust) = self.thrust(settings)? {
+                            if best.is_none_or(|(_, b)| thrust > b) {
+                                best = Some((chunk * chunk_size + i, thrust));
+                            }
+                        }
+                    }
+                    Ok(best)
+                })
+            }).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        let mut best: Option<(usize, Word)> = None;
+        for result in results {
+            if let Some((i, thrust)) = result? {
+                if best.is_none_or(|(_, b)| thrust > b) {
+                    best = Some((i, thrust));
+                }
+            }
+        }
+        Ok(best.map(|(i, thrust)| Best { settings: candidates[i].clone(), thrust }))
+    }
+}
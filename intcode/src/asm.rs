@@ -0,0 +1,257 @@
+//! A small assembly language for Intcode, using the same syntax the disassembler prints:
+//!
+//! ```text
+//! ; double every input until a zero is read
+//!         arb #y
+//! loop:   in [x]
+//!         jf [x], #done
+//!         mul [x], #2, rb+0
+//!         out rb+0
+//!         jt #1, #loop
+//! done:   hlt
+//! x:      db 0
+//! y:      db 0
+//! ```
+//!
+//! Operands are `[addr]` (position), `#imm` (immediate) or `rb+off` (relative). Values can be integers,
+//! labels, or sums and differences of them. `db` (or `data`) emits raw words, and a numeric label such
+//! as `12:` asserts the current address, so disassembler listings assemble back to the same program.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::error::Error;
+use std::fmt;
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::Word;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AsmError {
+    pub line: usize,
+    pub message: String
+}
+
+impl fmt::Display for AsmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "line {}: {}", self.line, self.message)
+    }
+}
+
+impl Error for AsmError {}
+
+enum Item<'a> {
+    Instr(Opcode, Vec<(Mode, &'a str)>),
+    Data(Vec<&'a str>)
+}
+
+fn is_label(s: &str) -> bool {
+    let mut chars = s.chars();
+    match chars.next() {
+        Some(c) if c.is_ascii_alphabetic() || c == '_' => chars.all(|c| c.is_ascii_alphanumeric() || c == '_'),
+        _ => false
+    }
+}
+
+fn parse_operand(s: &str) -> Result<(Mode, &str), String> {
+    let s = s.trim();
+    if s.starts_with('[') && s.ends_with(']') {
+        Ok((Mode::Position, &s[1..s.len() - 1]))
+    } else if let Some(rest) = s.strip_prefix('#') {
+        Ok((Mode::Immediate, rest))
+    } else if s == "rb" || (s.starts_with("rb") && !is_label(s)) {
+        let offset = &s[2..];
+        Ok((Mode::Relative, if offset.trim().is_empty() { "0" } else { offset }))
+    } else {
+        Err(format!("Operand needs a mode ([addr], #imm or rb+off): {}", s))
+    }
+}
+
+fn eval(expr: &str, labels: &HashMap<&str, usize>) -> Result<Word, String> {
+    let out_of_range = || format!("Value out of range: {}", expr.trim());
+    let mut total: i128 = 0;
+    let mut sign = 1;
+    let mut term = String::new();
+    let mut terms = 0;
+    for c in expr.chars().chain(Some('+')) {
+        if c == '+' || c == '-' {
+            let t = term.trim();
+            if !t.is_empty() {
+                let val = match t.parse::<i128>() {
+                    Ok(n) => n,
+                    Err(_) if t.chars().all(|c| c.is_ascii_digit()) => return Err(out_of_range()),
+                    Err(_) => *labels.get(t).ok_or(format!("Unknown label: {}", t))? as i128
+                };
+                total = total.checked_add(sign * val).ok_or_else(out_of_range)?;
+                terms += 1;
+                sign = 1;
+            }
+            if c == '-' {
+                sign = -sign;
+            }
+            term.clear();
+        } else {
+            term.push(c);
+        }
+    }
+    if terms == 0 {
+        Err(format!("Missing value: {}", expr))
+    } else {
+        Word::try_from(total).map_err(|_| out_of_range())
+    }
+}
+
+/// Assembles source text into program words, suitable for joining with commas.
+pub fn assemble(source: &str) -> Result<Vec<Word>, AsmError> {
+    let mut labels = HashMap::new();
+    let mut items = Vec::new();
+    let mut addr = 0;
+    for (i, line) in source.lines().enumerate() {
+        let err = |message| AsmError { line: i + 1, message };
+        let mut line = line.split(';').next().unwrap().trim();
+        while let Some(colon) = line.find(':') {
+            let label = line[..colon].trim();
+            if is_label(label) {
+                if labels.insert(label, addr).is_some() {
+                    return Err(err(format!("Duplicate label: {}", label)));
+                }
+            } else if let Ok(expected) = label.parse::<usize>() {
+                if expected != addr {
+                    return Err(err(format!("Expected address {} but assembling at {}", expected, addr)));
+                }
+            } else {
+                return Err(err(format!("Invalid label: {}", label)));
+            }
+            line = line[colon + 1..].trim_start();
+        }
+        if line.is_empty() {
+            continue;
+        }
+        let (name, rest) = match line.find(char::is_whitespace) {
+            Some(n) => (&line[..n], line[n..].trim()),
+            None => (line, "")
+        };
+        let args: Vec<_> = if rest.is_empty() { Vec::new() } else { rest.split(',').collect() };
+        if name == "db" || name == "data" {
+            if args.is_empty() {
+                return Err(err(format!("{} needs at least one value", name)));
+            }
+            addr += args.len();
+            items.push((i + 1, Item::Data(args)));
+        } else {
+            let opcode = Opcode::from_mnemonic(name).ok_or_else(|| err(format!("Unknown mnemonic: {}", name)))?;
+            if args.len() != opcode.arity() {
+                return Err(err(format!("{} takes {} operands, got {}", name, opcode.arity(), args.len())));
+            }
+            let operands = args.into_iter().map(parse_operand).collect::<Result<Vec<_>, _>>().map_err(err)?;
+            if let Some(w) = opcode.write_parm() {
+                if operands[w].0 == Mode::Immediate {
+                    return Err(err(format!("{} cannot write to an immediate operand", name)));
+                }
+            }
+            addr += opcode.arity() + 1;
+            items.push((i + 1, Item::Instr(opcode, operands)));
+        }
+    }
+
+    let mut program = Vec::with_capacity(addr);
+    for (line, item) in items {
+        let err = |message| AsmError { line, message };
+        match item {
+            Item::Data(values) => {
+                for v in values {
+                    program.push(eval(v, &labels).map_err(err)?);
+                }
+            }
+            Item::Instr(opcode, operands) => {
+                let mut modes = [Mode::Position; 3];
+                for (mode, (m, _)) in modes.iter_mut().zip(&operands) {
+                    *mode = *m;
+                }
+                program.push(Instruction { opcode, modes }.encode());
+                for (_, expr) in operands {
+                    program.push(eval(expr, &labels).map_err(err)?);
+                }
+            }
+        }
+    }
+    Ok(program)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::disasm::disassemble;
+    use crate::memory::MemoryKind;
+    use crate::tokenize;
+    use crate::vm::Vm;
+
+    /// The example from the module documentation.
+    const DOUBLER: &str = "; double every input until a zero is read
+        arb #y
+loop:   in [x]
+        jf [x], #done
+        mul [x], #2, rb+0
+        out rb+0
+        jt #1, #loop
+done:   hlt
+x:      db 0
+y:      db 0
+";
+
+    fn error(source: &str) -> AsmError {
+        assemble(source).unwrap_err()
+    }
+
+    #[test]
+    fn doc_example_runs() {
+        let program = assemble(DOUBLER).unwrap();
+        assert_eq!(program[..4], [109, 18, 3, 17]);
+        let mut vm = Vm::from_words(&program, &[3, 5, -4, 0], MemoryKind::Growable);
+        vm.run().unwrap();
+        assert_eq!(vm.outputs, [6, 10, -8]);
+    }
+
+    #[test]
+    fn disassembly_round_trips() {
+        let program = tokenize(include_str!("../../day9/input").trim_end()).unwrap();
+        assert_eq!(assemble(&disassemble(&program)), Ok(program));
+        let extremes = [1101, Word::MIN, Word::MAX, 7, 99, Word::MIN, Word::MAX, -1];
+        assert_eq!(assemble(&disassemble(&extremes)), Ok(extremes.to_vec()));
+    }
+
+    #[test]
+    fn operands() {
+        assert_eq!(assemble("add rb-3, #-1, rb"), Ok(vec![21201, -3, -1, 0]));
+        assert_eq!(assemble("out rb+ 4"), Ok(vec![204, 4]));
+        assert_eq!(assemble("a: out [b+1]\nb: jt #b - a, #-a-2\nhlt"), Ok(vec![4, 3, 1105, 2, -2, 99]));
+        assert_eq!(assemble("rbx: out [rbx]"), Ok(vec![4, 0]));
+        assert_eq!(assemble("db 1, 2, end-1\nend:"), Ok(vec![1, 2, 2]));
+        assert_eq!(assemble("db -9223372036854775807 - 1, -9223372036854775808, 9223372036854775807"),
+                   Ok(vec![Word::MIN, Word::MIN, Word::MAX]));
+        assert_eq!(assemble("db 9223372036854775807 + 1 - 2"), Ok(vec![Word::MAX - 1]));
+    }
+
+    #[test]
+    fn address_assertions() {
+        assert_eq!(assemble("0: in [2]\n2: hlt"), Ok(vec![3, 2, 99]));
+        assert_eq!(error("0: in [2]\n3: hlt"), AsmError { line: 2, message: "Expected address 3 but assembling at 2".to_owned() });
+    }
+
+    #[test]
+    fn errors() {
+        assert_eq!(error("a: hlt\na: hlt"), AsmError { line: 2, message: "Duplicate label: a".to_owned() });
+        assert_eq!(error("in #5"), AsmError { line: 1, message: "in cannot write to an immediate operand".to_owned() });
+        assert_eq!(error("hlt\nadd [1], [2], #3").message, "add cannot write to an immediate operand");
+        assert_eq!(error("out [nowhere]").message, "Unknown label: nowhere");
+        assert_eq!(error("out 5").message, "Operand needs a mode ([addr], #imm or rb+off): 5");
+        assert_eq!(error("out [1], [2]").message, "out takes 1 operands, got 2");
+        assert_eq!(error("nop").message, "Unknown mnemonic: nop");
+        assert_eq!(error("db").message, "db needs at least one value");
+        assert_eq!(error("out #").message, "Missing value: ");
+        assert_eq!(error("1a: hlt").message, "Invalid label: 1a");
+        assert_eq!(error("db 9223372036854775807 + 1").message, "Value out of range: 9223372036854775807 + 1");
+        assert_eq!(error("out #-9223372036854775807 - 2").message, "Value out of range: -9223372036854775807 - 2");
+        assert_eq!(error("db 99999999999999999999").message, "Value out of range: 99999999999999999999");
+        assert_eq!(error("db 1 + 999999999999999999999999999999999999999999").message,
+                   "Value out of range: 1 + 999999999999999999999999999999999999999999");
+    }
+}
@@ -0,0 +1,11 @@
+use std::error::Error;
+use std::io::{self, Read};
+use intcode::assemble;
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    let program: Vec<_> = assemble(&input)?.iter().map(|w| w.to_string()).collect();
+    println!("{}", program.join(","));
+    Ok(())
+}
@@ -0,0 +1,17 @@
+use std::env;
+use std::error::Error;
+use std::fs;
+use std::io;
+use intcode::{AsciiIo, MemoryKind, Vm, VmState};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let path = env::args().nth(1).ok_or("Usage: console <program>")?;
+    let mut vm = Vm::create(&fs::read_to_string(path)?, &[], MemoryKind::Growable)?;
+    let stdin = io::stdin();
+    let stdout = io::stdout();
+    vm.run_with(&mut AsciiIo::new(stdin.lock(), stdout.lock()))?;
+    if vm.state == VmState::Waiting {
+        eprintln!("(end of input, but the program is still waiting for more)");
+    }
+    Ok(())
+}
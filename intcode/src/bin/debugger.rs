@@ -0,0 +1,195 @@
+use std::collections::HashSet;
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::str::FromStr;
+use intcode::{format_instruction, MemoryKind, Snapshot, Step, Vm, VmState, Word};
+
+const HELP: &str = "\
+Commands:
+  s, step [n]         execute n instructions (default 1)
+  c, continue         run until a breakpoint, watchpoint, halt or missing input
+  b, break <pc>       stop before executing the instruction at pc
+  d, delete <pc>      remove a breakpoint
+  w, watch <addr>     stop after any write to addr
+  unwatch <addr>      remove a watchpoint
+  i, input <n>...     queue input values
+  pc | rb | regs      show registers
+  inputs | outputs    show the I/O queues
+  x, mem <addr> [n]   dump n words of memory (default 8, at most 1024)
+  save <file>         write a snapshot of the VM to a file
+  load <file>         replace the VM with a snapshot read from a file
+  l, list [addr] [n]  disassemble n instructions (default 8, at most 1024, from pc)
+  q, quit             exit";
+
+/// The most words `x` will dump, or instructions `list` will show, at once.
+const MAX_LISTING: usize = 1024;
+
+struct Debugger {
+    vm: Vm,
+    breakpoints: HashSet<usize>,
+    watchpoints: HashSet<usize>
+}
+
+fn words_at(vm: &Vm, addr: usize, n: usize) -> Vec<Word> {
+    (addr..addr.saturating_add(n)).map_while(|a| vm.mem.read(a).ok()).collect()
+}
+
+fn arg<T: FromStr>(args: &[&str], i: usize) -> Result<T, Box<dyn Error>> {
+    Ok(args.get(i).ok_or("Missing argument!")?.parse().map_err(|_| "Argument must be a number!")?)
+}
+
+fn arg_or<T: FromStr>(args: &[&str], i: usize, default: T) -> Result<T, Box<dyn Error>> {
+    if i < args.len() { arg(args, i) } else { Ok(default) }
+}
+
+impl Debugger {
+
+    fn show_next(&self) {
+        let words = words_at(&self.vm, self.vm.pc, 4);
+        if words.is_empty() {
+            println!("{:>5}: <beyond memory>", self.vm.pc);
+        } else {
+            println!("{:>5}: {}", self.vm.pc, format_instruction(&words).0);
+        }
+    }
+
+    fn show_step(&self, step: &Step) {
+        let mut line = format!("{:>5}: {}", step.pc, step.instr.opcode.mnemonic());
+        if let Some((addr, val)) = step.write {
+            line += &format!("  [{}] <- {}", addr, val);
+        }
+        if let Some(val) = step.output {
+            line += &format!("  out {}", val);
+        }
+        println!("{}", line);
+    }
+
+    fn show_stop(&self) {
+        match self.vm.state {
+            VmState::Halted => println!("Halted."),
+            VmState::Waiting => println!("Waiting for input."),
+            VmState::Ready => self.show_next()
+        }
+    }
+
+    /// Executes one instruction, returning whether it's okay to keep going.
+    fn step_once(&mut self, verbose: bool) -> bool {
+        match self.vm.step() {
+            Ok(Some(step)) => {
+                if verbose {
+                    self.show_step(&step);
+                }
+                if let Some((addr, val)) = step.write {
+                    if self.watchpoints.contains(&addr) {
+                        println!("Watchpoint: [{}] <- {} at pc {}", addr, val, step.pc);
+                        return false;
+                    }
+                }
+                self.vm.state == VmState::Ready
+            }
+            Ok(None) => false,
+            Err(e) => {
+                println!("Error: {}", e);
+                false
+            }
+        }
+    }
+
+    fn exec(&mut self, cmd: &str, args: &[&str]) -> Result<bool, Box<dyn Error>> {
+        match cmd {
+            "s" | "step" => {
+                let n = arg_or(args, 0, 1)?;
+                for _ in 0..n {
+                    if !self.step_once(true) {
+                        break;
+                    }
+                }
+                self.show_stop();
+            }
+            "c" | "continue" => {
+                while self.step_once(false) && !self.breakpoints.contains(&self.vm.pc) {}
+                if self.breakpoints.contains(&self.vm.pc) && self.vm.state == VmState::Ready {
+                    println!("Breakpoint at {}", self.vm.pc);
+                }
+                self.show_stop();
+            }
+            "b" | "break" => { self.breakpoints.insert(arg(args, 0)?); }
+            "d" | "delete" => { self.breakpoints.remove(&arg(args, 0)?); }
+            "w" | "watch" => { self.watchpoints.insert(arg(args, 0)?); }
+            "unwatch" => { self.watchpoints.remove(&arg(args, 0)?); }
+            "i" | "input" => {
+                for i in 0..args.len() {
+                    self.vm.inputs.push_back(arg(args, i)?);
+                }
+            }
+            "pc" => println!("{}", self.vm.pc),
+            "rb" => println!("{}", self.vm.rb),
+            "regs" => println!("pc={} rb={} state={:?}", self.vm.pc, self.vm.rb, self.vm.state),
+            "inputs" => println!("{:?}", self.vm.inputs),
+            "outputs" => println!("{:?}", self.vm.outputs),
+            "x" | "mem" => {
+                let addr = arg(args, 0)?;
+                let n = arg_or(args, 1, 8)?.min(MAX_LISTING);
+                for (i, chunk) in words_at(&self.vm, addr, n).chunks(8).enumerate() {
+                    println!("{:>5}: {:?}", addr + i * 8, chunk);
+                }
+            }
+            "l" | "list" => {
+                let mut addr = arg_or(args, 0, self.vm.pc)?;
+                let n = arg_or(args, 1, 8)?.min(MAX_LISTING);
+                for _ in 0..n {
+                    let words = words_at(&self.vm, addr, 4);
+                    if words.is_empty() {
+                        break;
+                    }
+                    let (text, size) = format_instruction(&words);
+                    println!("{} {:>5}: {}", if addr == self.vm.pc { ">" } else { " " }, addr, text);
+                    addr += size;
+                }
+            }
+            "save" => {
+                let path = args.first().ok_or("Missing file name!")?;
+                self.vm.snapshot().save(BufWriter::new(File::create(path)?))?;
+            }
+            "load" => {
+                let path = args.first().ok_or("Missing file name!")?;
+                self.vm.restore(&Snapshot::load(BufReader::new(File::open(path)?))?);
+                self.show_stop();
+            }
+            "h" | "help" => println!("{}", HELP),
+            "q" | "quit" => return Ok(false),
+            _ => println!("Unknown command: {} (try 'help')", cmd)
+        }
+        Ok(true)
+    }
+}
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<_> = env::args().collect();
+    let path = args.get(1).ok_or("Usage: debugger <program> [inputs...]")?;
+    let inputs = args[2..].iter().map(|s| s.parse()).collect::<Result<Vec<Word>, _>>()?;
+    let vm = Vm::create(&fs::read_to_string(path)?, &inputs, MemoryKind::Growable)?;
+    let mut dbg = Debugger { vm, breakpoints: HashSet::new(), watchpoints: HashSet::new() };
+    dbg.show_next();
+    let stdin = io::stdin();
+    loop {
+        print!("(idb) ");
+        io::stdout().flush()?;
+        let mut line = String::new();
+        if stdin.lock().read_line(&mut line)? == 0 {
+            break;
+        }
+        let mut words = line.split_whitespace();
+        if let Some(cmd) = words.next() {
+            let args: Vec<_> = words.collect();
+            match dbg.exec(cmd, &args) {
+                Ok(true) => {}
+                Ok(false) => break,
+                Err(e) => println!("{}", e)
+            }
+        }
+    }
+    Ok(())
+}
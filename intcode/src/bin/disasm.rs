@@ -0,0 +1,11 @@
+use std::error::Error;
+use std::io::{self, Read};
+use intcode::{disassemble, tokenize};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let mut input = String::new();
+    io::stdin().read_to_string(&mut input)?;
+    let program = tokenize(input.trim_end())?;
+    print!("{}", disassemble(&program));
+    Ok(())
+}
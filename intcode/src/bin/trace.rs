@@ -0,0 +1,22 @@
+use std::env;
+use std::error::Error;
+use std::fs::{self, File};
+use std::io::BufWriter;
+use intcode::{MemoryKind, Profiler, TraceWriter, Vm, Word};
+
+fn main() -> Result<(), Box<dyn Error>> {
+    let args: Vec<_> = env::args().collect();
+    if args.len() < 3 {
+        return Err(From::from("Usage: trace <program> <trace-file> [inputs...]"));
+    }
+    let inputs = args[3..].iter().map(|s| s.parse()).collect::<Result<Vec<Word>, _>>()?;
+    let mut vm = Vm::create(&fs::read_to_string(&args[1])?, &inputs, MemoryKind::Growable)?;
+    let mut tracer = (TraceWriter::new(BufWriter::new(File::create(&args[2])?)), Profiler::new());
+    let result = vm.run_traced(&mut tracer);
+    let (writer, profiler) = tracer;
+    writer.finish()?;
+    print!("{}", profiler);
+    println!("Final state: {:?}", vm.state);
+    println!("Outputs: {} words", vm.outputs.len());
+    Ok(result?)
+}
@@ -0,0 +1,276 @@
+//! Static control-flow analysis.
+//!
+//! The analysis follows the program as written, from address 0: it knows nothing about self-modifying
+//! code, and jumps whose target isn't an immediate operand are recorded as indirect, without guessing
+//! where they go.
+//!
+//! It does recognize the usual calling convention, though: a block that stores a constant at `rb+0`
+//! (pushing a return address) and then jumps unconditionally is taken to be a call, and the constant
+//! becomes another place control continues from once the callee returns through its indirect jump.
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write;
+use crate::disasm::format_instruction;
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::Word;
+
+/// How control leaves a basic block.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Exit {
+    /// Runs into the next block.
+    Fallthrough,
+    /// A conditional jump to an immediate target; the block has two successors.
+    Branch,
+    /// A jump that's always taken, because its condition is immediate.
+    Jump,
+    /// A `Jump` that pushed a return address first; the successors are the callee and the return address.
+    Call,
+    /// A jump whose target is only known at run time. The fall-through successor (if the condition isn't
+    /// immediate) is still recorded.
+    Indirect,
+    Halt,
+    /// The next word doesn't decode to an instruction, the instruction runs off the end of the program, or
+    /// it's a jump that's always taken to a negative address.
+    Invalid
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct BasicBlock {
+    pub start: usize,
+    /// Address just past the block's last instruction.
+    pub end: usize,
+    pub exit: Exit,
+    /// Blocks control can continue to, taken target first.
+    pub successors: Vec<usize>
+}
+
+/// The basic blocks reachable from address 0, by starting address.
+#[derive(Debug, Clone, Default)]
+pub struct Cfg {
+    pub blocks: BTreeMap<usize, BasicBlock>
+}
+
+/// The instruction at `addr`, if it decodes and fits in the program.
+fn instruction_at(program: &[Word], addr: usize) -> Option<Instruction> {
+    let instr = Instruction::decode(*program.get(addr)?).ok()?;
+    if addr + instr.size() <= program.len() { Some(instr) } else { None }
+}
+
+/// Where control can go after the instruction at `addr`: the exit kind if it ends a block, and the
+/// successor addresses.
+fn successors(program: &[Word], addr: usize) -> (Option<Exit>, Vec<usize>) {
+    let instr = match instruction_at(program, addr) {
+        Some(instr) => instr,
+        None => return (Some(Exit::Invalid), Vec::new())
+    };
+    let next = addr + instr.size();
+    match instr.opcode {
+        Opcode::Hlt => (Some(Exit::Halt), Vec::new()),
+        Opcode::Jt | Opcode::Jf => {
+            let cond = program[addr + 1];
+            let target = program[addr + 2];
+            let taken = match (instr.modes[0], instr.opcode) {
+                (Mode::Immediate, Opcode::Jt) => Some(cond != 0),
+                (Mode::Immediate, _) => Some(cond == 0),
+                _ => None
+            };
+            if instr.modes[1] == Mode::Immediate && target < 0 {
+                // taking the jump always faults, so only the fall-through (if any) goes anywhere
+                return if taken == Some(true) { (Some(Exit::Invalid), Vec::new()) } else { (None, vec![next]) };
+            }
+            let target = if instr.modes[1] == Mode::Immediate { Some(target as usize) } else { None };
+            match (taken, target) {
+                (Some(false), _) => (None, vec![next]),
+                (Some(true), Some(target)) => (Some(Exit::Jump), vec![target]),
+                (Some(true), None) => (Some(Exit::Indirect), Vec::new()),
+                (None, Some(target)) => (Some(Exit::Branch), vec![target, next]),
+                (None, None) => (Some(Exit::Indirect), vec![next])
+            }
+        }
+        _ => (None, vec![next])
+    }
+}
+
+/// The return address pushed by a block, if it stores a constant to `rb+0`.
+fn pushed_address(program: &[Word], start: usize, end: usize) -> Option<usize> {
+    let mut pushed = None;
+    let mut addr = start;
+    while addr < end {
+        let instr = instruction_at(program, addr)?;
+        let (a, b) = (program[addr + 1], program[addr + 2]);
+        let immediate = instr.modes[0] == Mode::Immediate && instr.modes[1] == Mode::Immediate;
+        if immediate && instr.modes[2] == Mode::Relative && program[addr + 3] == 0 {
+            match instr.opcode {
+                Opcode::Add => pushed = a.checked_add(b),
+                Opcode::Mul => pushed = a.checked_mul(b),
+                _ => {}
+            }
+        }
+        addr += instr.size();
+    }
+    pushed.filter(|&a| a >= 0 && (a as usize) < program.len()).map(|a| a as usize)
+}
+
+impl Cfg {
+
+    pub fn build(program: &[Word]) -> Self {
+        let mut roots = vec![0];
+        loop {
+            let cfg = Cfg::from_roots(program, &roots);
+            let returns: Vec<_> = cfg.blocks.values()
+                .filter(|block| block.exit == Exit::Call && !cfg.blocks.contains_key(&block.successors[1]))
+                .map(|block| block.successors[1])
+                .collect();
+            if returns.is_empty() {
+                return cfg;
+            }
+            roots.extend(returns);
+        }
+    }
+
+    fn from_roots(program: &[Word], roots: &[usize]) -> Self {
+        // find every reachable instruction, and the addresses that start blocks
+        let mut leaders: BTreeSet<_> = roots.iter().copied().collect();
+        let mut seen = BTreeSet::new();
+        let mut work = roots.to_vec();
+        while let Some(addr) = work.pop() {
+            if !seen.insert(addr) {
+                continue;
+            }
+            let (exit, succs) = successors(program, addr);
+            if exit.is_some() {
+                leaders.extend(&succs);
+            }
+            work.extend(succs);
+        }
+
+        let mut cfg = Cfg::default();
+        for &start in &leaders {
+            let mut addr = start;
+            let (mut exit, end, mut successors) = loop {
+                let (exit, succs) = successors(program, addr);
+                let end = instruction_at(program, addr).map_or(addr, |instr| addr + instr.size());
+                if let Some(exit) = exit {
+                    break (exit, end, succs);
+                }
+                if leaders.contains(&end) {
+                    break (Exit::Fallthrough, end, succs);
+                }
+                addr = end;
+            };
+            if exit == Exit::Jump {
+                if let Some(ret) = pushed_address(program, start, end) {
+                    exit = Exit::Call;
+                    successors.push(ret);
+                }
+            }
+            cfg.blocks.insert(start, BasicBlock { start, end, exit, successors });
+        }
+        cfg
+    }
+
+    /// Renders the graph in Graphviz DOT format, labelling each block with its disassembly.
+    pub fn to_dot(&self, program: &[Word]) -> String {
+        let mut dot = String::new();
+        writeln!(dot, "digraph intcode {{").unwrap();
+        writeln!(dot, "    node [shape=box, fontname=monospace];").unwrap();
+        let mut indirect = false;
+        for block in self.blocks.values() {
+            let mut label = String::new();
+            let mut addr = block.start;
+            let mut last = addr;
+            while addr < block.end {
+                let (text, size) = format_instruction(&program[addr..]);
+                write!(label, "{:>5}: {}\\l", addr, text).unwrap();
+                last = addr;
+                addr += size;
+            }
+            // the invalid word follows the block, unless the block ends in a jump to a negative address
+            let bad_jump = addr > block.start && successors(program, last).0 == Some(Exit::Invalid);
+            if block.exit == Exit::Invalid && !bad_jump {
+                write!(label, "{:>5}: <invalid>\\l", block.end).unwrap();
+            }
+            writeln!(dot, "    b{} [label=\"{}\"];", block.start, label).unwrap();
+            for (i, succ) in block.successors.iter().enumerate() {
+                let attrs = match (i, block.exit) {
+                    (0, Exit::Branch) | (0, Exit::Jump) => " [label=\"taken\"]",
+                    (0, Exit::Call) => " [label=\"call\"]",
+                    (1, Exit::Call) => " [label=\"return\", style=dashed]",
+                    _ => ""
+                };
+                writeln!(dot, "    b{} -> b{}{};", block.start, succ, attrs).unwrap();
+            }
+            if block.exit == Exit::Indirect {
+                indirect = true;
+                writeln!(dot, "    b{} -> indirect [style=dashed];", block.start).unwrap();
+            }
+        }
+        if indirect {
+            writeln!(dot, "    indirect [label=\"?\", shape=circle];").unwrap();
+        }
+        writeln!(dot, "}}").unwrap();
+        dot
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tokenize;
+
+    fn blocks(program: &str) -> Vec<(usize, usize, Exit, Vec<usize>)> {
+        let cfg = Cfg::build(&tokenize(program).unwrap());
+        cfg.blocks.into_values().map(|b| (b.start, b.end, b.exit, b.successors)).collect()
+    }
+
+    #[test]
+    fn branch() {
+        // jt [9], #6; out #1; hlt; out #2; hlt
+        assert_eq!(blocks("1005,9,6,104,1,99,104,2,99,0"), [
+            (0, 3, Exit::Branch, vec![6, 3]),
+            (3, 6, Exit::Halt, vec![]),
+            (6, 9, Exit::Halt, vec![])
+        ]);
+        // a branch never taken doesn't end the block
+        assert_eq!(blocks("1106,1,0,99"), [(0, 4, Exit::Halt, vec![])]);
+    }
+
+    #[test]
+    fn call_and_return() {
+        // add #7, #0, rb+0; jt #1, #8; hlt; jt #1, rb+0
+        assert_eq!(blocks("21101,7,0,0,1105,1,8,99,2105,1,0"), [
+            (0, 7, Exit::Call, vec![8, 7]),
+            (7, 8, Exit::Halt, vec![]),
+            (8, 11, Exit::Indirect, vec![])
+        ]);
+        // a pushed value that overflows isn't a return address
+        assert_eq!(blocks("21101,9223372036854775807,1,0,1105,1,9,0,0,99"), [
+            (0, 7, Exit::Jump, vec![9]),
+            (9, 10, Exit::Halt, vec![])
+        ]);
+        assert_eq!(blocks("21102,4611686018427387904,2,0,1105,1,9,0,0,99")[0].2, Exit::Jump);
+    }
+
+    #[test]
+    fn indirect_jump() {
+        // jt [5], [6]; hlt
+        assert_eq!(blocks("5,5,6,99,0,0"), [(0, 3, Exit::Indirect, vec![3]), (3, 4, Exit::Halt, vec![])]);
+    }
+
+    #[test]
+    fn negative_jump() {
+        assert_eq!(blocks("1105,1,-1"), [(0, 3, Exit::Invalid, vec![])]);
+        // only the fall-through of a conditional one goes anywhere
+        assert_eq!(blocks("1005,4,-1,99,0"), [(0, 4, Exit::Halt, vec![])]);
+        let program = tokenize("1105,1,-1,99").unwrap();
+        assert!(!Cfg::build(&program).to_dot(&program).contains("<invalid>"));
+    }
+
+    #[test]
+    fn invalid_instruction() {
+        let program = tokenize("104,1,98").unwrap();
+        let cfg = Cfg::build(&program);
+        assert_eq!(cfg.blocks[&0].exit, Exit::Invalid);
+        assert_eq!(cfg.blocks[&0].end, 2);
+        assert!(cfg.to_dot(&program).contains("    2: <invalid>"));
+    }
+}
@@ -0,0 +1,46 @@
+use std::fmt::Write;
+use crate::instruction::{Instruction, Mode};
+use crate::Word;
+
+fn format_operand(mode: Mode, value: Word) -> String {
+    match mode {
+        Mode::Position => format!("[{}]", value),
+        Mode::Immediate => format!("#{}", value),
+        Mode::Relative if value < 0 => format!("rb{}", value),
+        Mode::Relative => format!("rb+{}", value)
+    }
+}
+
+/// Renders the instruction at the start of `words`, returning the text and how many words it used.
+/// Words that don't decode to a complete instruction, or that carry mode digits the instruction doesn't
+/// use (so that reassembling would change them), are rendered as a one-word `data` directive.
+pub fn format_instruction(words: &[Word]) -> (String, usize) {
+    match Instruction::decode(words[0]) {
+        Ok(instr) if instr.size() <= words.len() && instr.encode() == words[0] => {
+            let operands: Vec<_> = (0..instr.opcode.arity())
+                .map(|i| format_operand(instr.modes[i], words[i + 1]))
+                .collect();
+            let text = if operands.is_empty() {
+                instr.opcode.mnemonic().to_owned()
+            } else {
+                format!("{} {}", instr.opcode.mnemonic(), operands.join(", "))
+            };
+            (text, instr.size())
+        }
+        _ => (format!("data {}", words[0]), 1)
+    }
+}
+
+/// Produces a listing of the whole program, one instruction or data word per line, using a linear sweep
+/// from address 0.
+pub fn disassemble(program: &[Word]) -> String {
+    let mut listing = String::new();
+    let mut addr = 0;
+    while addr < program.len() {
+        let (text, size) = format_instruction(&program[addr..]);
+        let raw: Vec<_> = program[addr..addr + size].iter().map(|w| w.to_string()).collect();
+        writeln!(listing, "{:>5}: {:<32} ; {}", addr, text, raw.join(",")).unwrap();
+        addr += size;
+    }
+    listing
+}
@@ -0,0 +1,48 @@
+use std::error::Error;
+use std::fmt;
+use crate::Word;
+
+/// What went wrong while executing a single instruction.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Fault {
+    UnknownOpcode(Word),
+    BadMode(Word),
+    WriteInImmediateMode,
+    NegativeAddress(Word),
+    AddressOutOfRange(usize),
+    /// An addition or multiplication (including an address or relative base adjustment) that doesn't fit
+    /// in a `Word`.
+    Overflow
+}
+
+/// A fault together with the instruction that raised it.
+///
+/// If the fault happened while fetching the instruction itself (e.g. the program counter ran off the
+/// end of memory), `instr` is 0.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct VmError {
+    pub pc: usize,
+    pub instr: Word,
+    pub fault: Fault
+}
+
+impl fmt::Display for Fault {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Fault::UnknownOpcode(op) => write!(f, "unknown opcode {}", op),
+            Fault::BadMode(mode) => write!(f, "bad parameter mode {}", mode),
+            Fault::WriteInImmediateMode => write!(f, "immediate mode for a write parameter"),
+            Fault::NegativeAddress(addr) => write!(f, "negative address {}", addr),
+            Fault::AddressOutOfRange(addr) => write!(f, "address {} is beyond memory", addr),
+            Fault::Overflow => write!(f, "arithmetic overflow")
+        }
+    }
+}
+
+impl fmt::Display for VmError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} at pc {} (instruction {})", self.fault, self.pc, self.instr)
+    }
+}
+
+impl Error for VmError {}
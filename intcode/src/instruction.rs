@@ -0,0 +1,164 @@
+use crate::error::Fault;
+use crate::Word;
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Opcode {
+    Add,
+    Mul,
+    In,
+    Out,
+    Jt,
+    Jf,
+    Lt,
+    Eq,
+    Arb,
+    Hlt
+}
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Mode {
+    Position,
+    Immediate,
+    Relative
+}
+
+/// A decoded instruction word: the opcode plus the mode of each of its parameters.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Instruction {
+    pub opcode: Opcode,
+    pub modes: [Mode; 3]
+}
+
+impl Opcode {
+
+    pub fn from_word(op: Word) -> Result<Self, Fault> {
+        match op {
+            1 => Ok(Opcode::Add),
+            2 => Ok(Opcode::Mul),
+            3 => Ok(Opcode::In),
+            4 => Ok(Opcode::Out),
+            5 => Ok(Opcode::Jt),
+            6 => Ok(Opcode::Jf),
+            7 => Ok(Opcode::Lt),
+            8 => Ok(Opcode::Eq),
+            9 => Ok(Opcode::Arb),
+            99 => Ok(Opcode::Hlt),
+            _ => Err(Fault::UnknownOpcode(op))
+        }
+    }
+
+    pub fn to_word(self) -> Word {
+        match self {
+            Opcode::Add => 1,
+            Opcode::Mul => 2,
+            Opcode::In => 3,
+            Opcode::Out => 4,
+            Opcode::Jt => 5,
+            Opcode::Jf => 6,
+            Opcode::Lt => 7,
+            Opcode::Eq => 8,
+            Opcode::Arb => 9,
+            Opcode::Hlt => 99
+        }
+    }
+
+    pub fn from_mnemonic(name: &str) -> Option<Self> {
+        match name {
+            "add" => Some(Opcode::Add),
+            "mul" => Some(Opcode::Mul),
+            "in" => Some(Opcode::In),
+            "out" => Some(Opcode::Out),
+            "jt" => Some(Opcode::Jt),
+            "jf" => Some(Opcode::Jf),
+            "lt" => Some(Opcode::Lt),
+            "eq" => Some(Opcode::Eq),
+            "arb" => Some(Opcode::Arb),
+            "hlt" => Some(Opcode::Hlt),
+            _ => None
+        }
+    }
+
+    pub fn mnemonic(self) -> &'static str {
+        match self {
+            Opcode::Add => "add",
+            Opcode::Mul => "mul",
+            Opcode::In => "in",
+            Opcode::Out => "out",
+            Opcode::Jt => "jt",
+            Opcode::Jf => "jf",
+            Opcode::Lt => "lt",
+            Opcode::Eq => "eq",
+            Opcode::Arb => "arb",
+            Opcode::Hlt => "hlt"
+        }
+    }
+
+    /// Number of parameters following the opcode.
+    pub fn arity(self) -> usize {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => 3,
+            Opcode::Jt | Opcode::Jf => 2,
+            Opcode::In | Opcode::Out | Opcode::Arb => 1,
+            Opcode::Hlt => 0
+        }
+    }
+
+    /// Index of the parameter this opcode writes to, if any.
+    pub fn write_parm(self) -> Option<usize> {
+        match self {
+            Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => Some(2),
+            Opcode::In => Some(0),
+            _ => None
+        }
+    }
+}
+
+impl Mode {
+
+    pub fn from_word(mode: Word) -> Result<Self, Fault> {
+        match mode {
+            0 => Ok(Mode::Position),
+            1 => Ok(Mode::Immediate),
+            2 => Ok(Mode::Relative),
+            _ => Err(Fault::BadMode(mode))
+        }
+    }
+
+    pub fn to_word(self) -> Word {
+        match self {
+            Mode::Position => 0,
+            Mode::Immediate => 1,
+            Mode::Relative => 2
+        }
+    }
+}
+
+impl Instruction {
+
+    fn mode_for(i: u32, modes: Word) -> Word {
+        modes / 10_i64.pow(i) % 10
+    }
+
+    /// Decodes an instruction word, rejecting unknown opcodes, unknown modes and writes in immediate mode.
+    pub fn decode(instr: Word) -> Result<Self, Fault> {
+        let opcode = Opcode::from_word(instr % 100)?;
+        let mut modes = [Mode::Position; 3];
+        for (i, mode) in modes.iter_mut().enumerate().take(opcode.arity()) {
+            *mode = Mode::from_word(Instruction::mode_for(i as u32, instr / 100))?;
+            if *mode == Mode::Immediate && opcode.write_parm() == Some(i) {
+                return Err(Fault::WriteInImmediateMode);
+            }
+        }
+        Ok(Self { opcode, modes })
+    }
+
+    pub fn encode(&self) -> Word {
+        let modes = self.modes.iter().rev().fold(0, |acc, m| acc * 10 + m.to_word());
+        modes * 100 + self.opcode.to_word()
+    }
+
+    /// Number of words the instruction occupies, including the opcode.
+    pub fn size(&self) -> usize {
+        self.opcode.arity() + 1
+    }
+}
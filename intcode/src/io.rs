@@ -0,0 +1,150 @@
+use std::collections::VecDeque;
+use std::io::{self, BufRead, Write};
+use std::sync::mpsc::{Receiver, Sender};
+use crate::Word;
+
+/// Where a VM gets its inputs and sends its outputs.
+pub trait Io {
+    /// Returns the next input, or `None` if there isn't one available, in which case the VM stops and
+    /// waits.
+    fn input(&mut self) -> Option<Word>;
+    fn output(&mut self, value: Word);
+}
+
+/// Plain input and output queues. This is what `Vm::run` uses with the VM's own `inputs` and `outputs`.
+#[derive(Debug, Clone, Default)]
+pub struct Queues {
+    pub inputs: VecDeque<Word>,
+    pub outputs: VecDeque<Word>
+}
+
+impl Io for Queues {
+    fn input(&mut self) -> Option<Word> {
+        self.inputs.pop_front()
+    }
+
+    fn output(&mut self, value: Word) {
+        self.outputs.push_back(value);
+    }
+}
+
+/// Calls `input` whenever the program reads and `output` whenever it writes.
+pub struct Closures<I: FnMut() -> Option<Word>, O: FnMut(Word)> {
+    pub input: I,
+    pub output: O
+}
+
+impl<I: FnMut() -> Option<Word>, O: FnMut(Word)> Io for Closures<I, O> {
+    fn input(&mut self) -> Option<Word> {
+        (self.input)()
+    }
+
+    fn output(&mut self, value: Word) {
+        (self.output)(value)
+    }
+}
+
+/// Reads one number per line from stdin and prints one number per line to stdout. Lines that aren't
+/// numbers are reported on stderr and skipped; the VM waits once stdin is exhausted.
+#[derive(Debug, Default)]
+pub struct StdIo;
+
+impl Io for StdIo {
+    fn input(&mut self) -> Option<Word> {
+        let stdin = io::stdin();
+        for line in stdin.lock().lines() {
+            let line = line.ok()?;
+            match line.trim().parse() {
+                Ok(n) => return Some(n),
+                Err(_) => eprintln!("Not a number: {}", line.trim())
+            }
+        }
+        None
+    }
+
+    fn output(&mut self, value: Word) {
+        println!("{}", value);
+    }
+}
+
+/// Encodes a line of text as program input, adding the trailing newline.
+pub fn ascii_line(line: &str) -> Vec<Word> {
+    line.bytes().chain(Some(b'\n')).map(Word::from).collect()
+}
+
+/// The text for one output value, given whether the text so far ends a line. Values that aren't ASCII
+/// characters become decimal numbers on a line of their own.
+fn ascii_bytes(value: Word, line_start: bool) -> Vec<u8> {
+    if (0..128).contains(&value) {
+        vec![value as u8]
+    } else if line_start {
+        format!("{}\n", value).into_bytes()
+    } else {
+        format!("\n{}\n", value).into_bytes()
+    }
+}
+
+/// Renders program output as text, the same way `AsciiIo` does.
+pub fn ascii_text<I: IntoIterator<Item = Word>>(outputs: I) -> String {
+    let mut text = Vec::new();
+    for value in outputs {
+        let line_start = text.last().is_none_or(|&c| c == b'\n');
+        text.extend(ascii_bytes(value, line_start));
+    }
+    // everything is ASCII
+    String::from_utf8(text).unwrap()
+}
+
+/// Treats input and output as ASCII text. Each line read from `reader` is fed to the program one
+/// character at a time, including its trailing newline. Output values that aren't ASCII characters are
+/// written as decimal numbers on a line of their own.
+pub struct AsciiIo<R: BufRead, W: Write> {
+    reader: R,
+    writer: W,
+    pending: VecDeque<Word>,
+    line_start: bool
+}
+
+impl<R: BufRead, W: Write> AsciiIo<R, W> {
+    pub fn new(reader: R, writer: W) -> Self {
+        Self { reader, writer, pending: VecDeque::new(), line_start: true }
+    }
+}
+
+impl<R: BufRead, W: Write> Io for AsciiIo<R, W> {
+    fn input(&mut self) -> Option<Word> {
+        if self.pending.is_empty() {
+            let _ = self.writer.flush();
+            let mut line = String::new();
+            if self.reader.read_line(&mut line).ok()? == 0 {
+                return None;
+            }
+            self.pending.extend(ascii_line(line.trim_end_matches('\n')));
+        }
+        self.pending.pop_front()
+    }
+
+    fn output(&mut self, value: Word) {
+        let bytes = ascii_bytes(value, self.line_start);
+        self.line_start = bytes.last() == Some(&b'\n');
+        let _ = self.writer.write_all(&bytes);
+    }
+}
+
+/// Receives inputs from one channel and sends outputs to another, so VMs can talk across threads.
+/// Reading blocks until a value arrives; the VM waits if every sender has gone away.
+pub struct ChannelIo {
+    pub rx: Receiver<Word>,
+    pub tx: Sender<Word>
+}
+
+impl Io for ChannelIo {
+    fn input(&mut self) -> Option<Word> {
+        self.rx.recv().ok()
+    }
+
+    fn output(&mut self, value: Word) {
+        // nobody listening anymore isn't the program's problem
+        let _ = self.tx.send(value);
+    }
+}
@@ -0,0 +1,37 @@
+//! Shared Intcode interpreter used by the individual day binaries.
+
+use std::num::ParseIntError;
+
+mod amplifier;
+mod asm;
+mod cfg;
+mod disasm;
+mod error;
+mod instruction;
+mod io;
+mod memory;
+mod network;
+mod snapshot;
+mod symbolic;
+mod trace;
+mod vm;
+
+pub use amplifier::{Amplifiers, Best, Topology};
+pub use asm::{assemble, AsmError};
+pub use cfg::{BasicBlock, Cfg, Exit};
+pub use disasm::{disassemble, format_instruction};
+pub use error::{Fault, VmError};
+pub use instruction::{Instruction, Mode, Opcode};
+pub use io::{ascii_line, ascii_text, AsciiIo, ChannelIo, Closures, Io, Queues, StdIo};
+pub use memory::{Memory, MemoryKind, GROWABLE_LIMIT_WORDS, PAGE_SIZE};
+pub use network::{Network, NetworkError, NodeId, Outcome};
+pub use snapshot::Snapshot;
+pub use symbolic::{Expr, InputSearch, Linear, Method, Solution};
+pub use trace::{Profiler, TraceWriter, Tracer};
+pub use vm::{Step, Vm, VmState};
+
+pub type Word = i64;
+
+pub fn tokenize(input: &str) -> Result<Vec<Word>, ParseIntError> {
+    input.split(',').map(|s| s.parse()).collect()
+}
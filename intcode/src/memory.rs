@@ -0,0 +1,145 @@
+use std::collections::BTreeMap;
+use std::ops::{Index, IndexMut};
+use std::sync::Arc;
+use crate::error::Fault;
+use crate::Word;
+
+/// Number of words in each page of a `Memory::Paged` backend.
+pub const PAGE_SIZE: usize = 1024;
+
+/// Largest size a `Memory::Growable` backend will grow to. Programs that touch addresses beyond this
+/// should use `Memory::Paged` instead.
+pub const GROWABLE_LIMIT_WORDS: usize = 1 << 24;
+
+static ZERO: Word = 0;
+
+/// Which memory backend a VM should be created with.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum MemoryKind {
+    /// A flat memory of at least this many words; touching anything past the end is a fault.
+    Fixed(usize),
+    /// A flat memory that grows to fit whatever address is written, up to `GROWABLE_LIMIT_WORDS`.
+    Growable,
+    /// A sparse memory that only allocates the pages that are actually written.
+    Paged
+}
+
+/// Program memory. Unwritten addresses read as zero in the growable and paged backends.
+///
+/// Cloning is cheap: clones share their words (or, for the paged backend, each page) until one side
+/// writes to them, at which point only the written vector or page is copied.
+#[derive(Debug, Clone)]
+pub enum Memory {
+    Fixed(Arc<Vec<Word>>),
+    Growable(Arc<Vec<Word>>),
+    Paged(BTreeMap<usize, Arc<[Word; PAGE_SIZE]>>)
+}
+
+impl Memory {
+
+    pub fn new(kind: MemoryKind, program: &[Word]) -> Self {
+        match kind {
+            MemoryKind::Fixed(size) => {
+                let mut words = vec![0; size.max(program.len())];
+                words[0..program.len()].copy_from_slice(program);
+                Memory::Fixed(Arc::new(words))
+            }
+            MemoryKind::Growable => Memory::Growable(Arc::new(program.to_vec())),
+            MemoryKind::Paged => {
+                let mut mem = Memory::Paged(BTreeMap::new());
+                for (addr, &val) in program.iter().enumerate() {
+                    mem[addr] = val;
+                }
+                mem
+            }
+        }
+    }
+
+    pub fn kind(&self) -> MemoryKind {
+        match self {
+            Memory::Fixed(words) => MemoryKind::Fixed(words.len()),
+            Memory::Growable(_) => MemoryKind::Growable,
+            Memory::Paged(_) => MemoryKind::Paged
+        }
+    }
+
+    /// The words actually stored by the backend, as (start address, words) runs in address order.
+    pub fn segments(&self) -> Vec<(usize, &[Word])> {
+        match self {
+            Memory::Fixed(words) | Memory::Growable(words) => vec![(0, &words[..])],
+            Memory::Paged(pages) => pages.iter().map(|(&i, page)| (i * PAGE_SIZE, &page[..])).collect()
+        }
+    }
+
+    pub fn read(&self, addr: usize) -> Result<Word, Fault> {
+        match self {
+            Memory::Fixed(words) => words.get(addr).copied().ok_or(Fault::AddressOutOfRange(addr)),
+            Memory::Growable(words) => Ok(words.get(addr).copied().unwrap_or(0)),
+            Memory::Paged(_) => Ok(self[addr])
+        }
+    }
+
+    /// Reads the `out.len()` words starting at `addr`, all at once where the backend allows it.
+    pub fn read_into(&self, addr: usize, out: &mut [Word]) -> Result<(), Fault> {
+        if let Memory::Fixed(words) | Memory::Growable(words) = self {
+            if let Some(run) = words.get(addr..addr + out.len()) {
+                out.copy_from_slice(run);
+                return Ok(());
+            }
+        }
+        for (i, word) in out.iter_mut().enumerate() {
+            *word = self.read(addr + i)?;
+        }
+        Ok(())
+    }
+
+    /// Checks that `write` would succeed at `addr`, without writing anything.
+    pub fn check_write(&self, addr: usize) -> Result<(), Fault> {
+        match self {
+            Memory::Fixed(words) if addr >= words.len() => Err(Fault::AddressOutOfRange(addr)),
+            Memory::Growable(_) if addr >= GROWABLE_LIMIT_WORDS => Err(Fault::AddressOutOfRange(addr)),
+            _ => Ok(())
+        }
+    }
+
+    pub fn write(&mut self, addr: usize, val: Word) -> Result<(), Fault> {
+        self.check_write(addr)?;
+        self[addr] = val;
+        Ok(())
+    }
+}
+
+impl Index<usize> for Memory {
+    type Output = Word;
+
+    fn index(&self, addr: usize) -> &Word {
+        match self {
+            Memory::Fixed(words) => &words[addr],
+            Memory::Growable(words) => words.get(addr).unwrap_or(&ZERO),
+            Memory::Paged(pages) => match pages.get(&(addr / PAGE_SIZE)) {
+                Some(page) => &page[addr % PAGE_SIZE],
+                None => &ZERO
+            }
+        }
+    }
+}
+
+impl IndexMut<usize> for Memory {
+    fn index_mut(&mut self, addr: usize) -> &mut Word {
+        match self {
+            Memory::Fixed(words) => &mut Arc::make_mut(words)[addr],
+            Memory::Growable(words) => {
+                let words = Arc::make_mut(words);
+                if addr >= words.len() {
+                    assert!(addr < GROWABLE_LIMIT_WORDS, "Address {} is beyond the growable memory limit.", addr);
+                    words.resize(addr + 1, 0);
+                }
+                &mut words[addr]
+            }
+            Memory::Paged(pages) => {
+                let page = pages.entry(addr / PAGE_SIZE).or_insert_with(|| Arc::new([0; PAGE_SIZE]));
+                &mut Arc::make_mut(page)[addr % PAGE_SIZE]
+            }
+        }
+    }
+}
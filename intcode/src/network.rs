@@ -0,0 +1,339 @@
+//! Several VMs wired together, each one's outputs feeding other VMs' inputs.
+//!
+//! A node with one outgoing connection pipes into that node; a node with several broadcasts every output
+//! to all of them; a node with several incoming connections receives their outputs interleaved in the
+//! order they were produced. Outputs of a node with no outgoing connections stay in its `outputs` queue.
+use std::collections::VecDeque;
+use std::error::Error;
+use std::fmt;
+use std::mem;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Condvar, Mutex};
+use std::thread;
+use crate::error::VmError;
+use crate::io::Io;
+use crate::vm::{Vm, VmState};
+use crate::Word;
+
+pub type NodeId = usize;
+
+/// How a network run ended.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Outcome {
+    /// Every VM halted.
+    Halted,
+    /// Every VM that hasn't halted is waiting for input that nobody is going to send.
+    Deadlocked
+}
+
+/// A VM error, together with the node that raised it.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct NetworkError {
+    pub node: NodeId,
+    pub error: VmError
+}
+
+impl fmt::Display for NetworkError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "node {}: {}", self.node, self.error)
+    }
+}
+
+impl Error for NetworkError {}
+
+#[derive(Debug)]
+struct Node {
+    vm: Vm,
+    targets: Vec<NodeId>,
+    last_output: Option<Word>
+}
+
+#[derive(Debug, Default)]
+pub struct Network {
+    nodes: Vec<Node>
+}
+
+impl Network {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, vm: Vm) -> NodeId {
+        self.nodes.push(Node { vm, targets: Vec::new(), last_output: None });
+        self.nodes.len() - 1
+    }
+
+    /// Sends everything `from` outputs to `to` as well as to any other nodes it's already connected to.
+    pub fn connect(&mut self, from: NodeId, to: NodeId) {
+        self.nodes[from].targets.push(to);
+    }
+
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn vm(&self, id: NodeId) -> &Vm {
+        &self.nodes[id].vm
+    }
+
+    pub fn vm_mut(&mut self, id: NodeId) -> &mut Vm {
+        &mut self.nodes[id].vm
+    }
+
+    /// The most recent value the node output, whether or not it was sent anywhere.
+    pub fn last_output(&self, id: NodeId) -> Option<Word> {
+        self.nodes[id].last_output
+    }
+
+    pub fn into_vms(self) -> Vec<Vm> {
+        self.nodes.into_iter().map(|node| node.vm).collect()
+    }
+
+    /// Runs every VM in turn on the current thread until they've all halted or deadlocked.
+    pub fn run(&mut self) -> Result<Outcome, NetworkError> {
+        loop {
+            for id in 0..self.nodes.len() {
+                let node = &mut self.nodes[id];
+                if node.vm.state == VmState::Halted {
+                    continue;
+                }
+                let before = node.vm.outputs.len();
+                node.vm.run().map_err(|error| NetworkError { node: id, error })?;
+                if node.vm.outputs.len() > before {
+                    node.last_output = node.vm.outputs.back().copied();
+                }
+                if node.targets.is_empty() {
+                    continue;
+                }
+                let outputs: Vec<_> = node.vm.outputs.drain(..).collect();
+                let targets = node.targets.clone();
+                for to in targets {
+                    self.nodes[to].vm.inputs.extend(&outputs);
+                }
+            }
+            if self.nodes.iter().all(|node| node.vm.state == VmState::Halted) {
+                return Ok(Outcome::Halted);
+            }
+            if self.nodes.iter().all(|node| node.vm.state == VmState::Halted || node.vm.inputs.is_empty()) {
+                return Ok(Outcome::Deadlocked);
+            }
+        }
+    }
+
+    /// Like `run`, but gives every VM its own thread, passing values between them over channels.
+    pub fn run_threaded(&mut self) -> Result<Outcome, NetworkError> {
+        let counts = Counts { live: self.nodes.len(), ..Counts::default() };
+        let monitor = Monitor { counts: Mutex::new(counts), changed: Condvar::new() };
+        let (senders, receivers): (Vec<Sender<Word>>, Vec<Receiver<Word>>) =
+            self.nodes.iter().map(|_| mpsc::channel()).unzip();
+        let results: Vec<_> = thread::scope(|s| {
+            let handles: Vec<_> = self.nodes.iter_mut().zip(receivers).enumerate().map(|(id, (node, rx))| {
+                let mut io = NodeIo {
+                    queued: mem::take(&mut node.vm.inputs),
+                    rx,
+                    targets: node.targets.iter().map(|&to| senders[to].clone()).collect(),
+                    kept: mem::take(&mut node.vm.outputs),
+                    last_output: node.last_output,
+                    monitor: &monitor
+                };
+                s.spawn(move || {
+                    let result = node.vm.run_with(&mut io);
+                    let leftovers = io.monitor.finished(io.rx, result.is_err());
+                    node.vm.inputs = io.queued;
+                    node.vm.inputs.extend(leftovers);
+                    node.vm.outputs = io.kept;
+                    node.last_output = io.last_output;
+                    result.map_err(|error| NetworkError { node: id, error })
+                })
+            }).collect();
+            handles.into_iter().map(|handle| handle.join().unwrap()).collect()
+        });
+        results.into_iter().collect::<Result<(), _>>()?;
+        if self.nodes.iter().all(|node| node.vm.state == VmState::Halted) {
+            Ok(Outcome::Halted)
+        } else {
+            Ok(Outcome::Deadlocked)
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+struct Counts {
+    /// Threads whose VM is still running.
+    live: usize,
+    /// Threads blocked waiting for input.
+    blocked: usize,
+    /// Values sent but not yet received.
+    in_flight: usize,
+    deadlocked: bool,
+    /// Some VM faulted, so the rest should stop rather than wait for input.
+    failed: bool
+}
+
+/// Lets the threads of `Network::run_threaded` notice when all of them are stuck.
+struct Monitor {
+    counts: Mutex<Counts>,
+    changed: Condvar
+}
+
+impl Monitor {
+
+    fn check_deadlock(counts: &mut Counts) {
+        if counts.live > 0 && counts.blocked == counts.live && counts.in_flight == 0 {
+            counts.deadlocked = true;
+        }
+    }
+
+    /// Called when a thread's VM stops for good, `faulted` or not. Returns whatever was sent to it that it
+    /// never read.
+    fn finished(&self, rx: Receiver<Word>, faulted: bool) -> Vec<Word> {
+        let mut counts = self.counts.lock().unwrap();
+        counts.failed |= faulted;
+        let leftovers: Vec<_> = rx.try_iter().collect();
+        // dropping the receiver while still holding the lock, so later sends fail instead of going astray
+        drop(rx);
+        counts.in_flight -= leftovers.len();
+        counts.live -= 1;
+        Monitor::check_deadlock(&mut counts);
+        self.changed.notify_all();
+        leftovers
+    }
+}
+
+struct NodeIo<'a> {
+    queued: VecDeque<Word>,
+    rx: Receiver<Word>,
+    targets: Vec<Sender<Word>>,
+    kept: VecDeque<Word>,
+    last_output: Option<Word>,
+    monitor: &'a Monitor
+}
+
+impl Io for NodeIo<'_> {
+    fn input(&mut self) -> Option<Word> {
+        if let Some(n) = self.queued.pop_front() {
+            return Some(n);
+        }
+        let mut counts = self.monitor.counts.lock().unwrap();
+        loop {
+            if let Ok(n) = self.rx.try_recv() {
+                counts.in_flight -= 1;
+                return Some(n);
+            }
+            if counts.deadlocked || counts.failed {
+                return None;
+            }
+            counts.blocked += 1;
+            Monitor::check_deadlock(&mut counts);
+            if counts.deadlocked {
+                counts.blocked -= 1;
+                self.monitor.changed.notify_all();
+                return None;
+            }
+            counts = self.monitor.changed.wait(counts).unwrap();
+            counts.blocked -= 1;
+        }
+    }
+
+    fn output(&mut self, value: Word) {
+        self.last_output = Some(value);
+        if self.targets.is_empty() {
+            self.kept.push_back(value);
+            return;
+        }
+        let mut counts = self.monitor.counts.lock().unwrap();
+        for tx in &self.targets {
+            // sending fails only if the target has stopped for good, and then nobody cares
+            if tx.send(value).is_ok() {
+                counts.in_flight += 1;
+            }
+        }
+        self.monitor.changed.notify_all();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Fault;
+    use crate::memory::MemoryKind;
+
+    /// Builds a network of `programs` connected by `links`, and runs it both ways, checking that they
+    /// agree (on every node, unless a fault stopped the single-threaded run partway through a round).
+    /// Returns the outcome and the VMs from the threaded run.
+    fn run_both(programs: &[&str], links: &[(NodeId, NodeId)]) -> (Result<Outcome, NetworkError>, Network) {
+        let build = || {
+            let mut net = Network::new();
+            for program in programs {
+                net.add(Vm::create(program, &[], MemoryKind::Growable).unwrap());
+            }
+            for &(from, to) in links {
+                net.connect(from, to);
+            }
+            net
+        };
+        let mut single = build();
+        let mut threaded = build();
+        let outcome = single.run();
+        assert_eq!(threaded.run_threaded(), outcome);
+        for id in (0..programs.len()).filter(|_| outcome.is_ok()) {
+            assert_eq!(threaded.vm(id).state, single.vm(id).state, "node {}", id);
+            assert_eq!(threaded.vm(id).inputs, single.vm(id).inputs, "node {}", id);
+            assert_eq!(threaded.vm(id).outputs, single.vm(id).outputs, "node {}", id);
+            assert_eq!(threaded.last_output(id), single.last_output(id), "node {}", id);
+        }
+        (outcome, threaded)
+    }
+
+    #[test]
+    fn pipe_halts() {
+        // 5 and 6 into a node that adds its two inputs
+        let (outcome, net) = run_both(&["104,5,104,6,99", "3,11,3,12,1,11,12,13,4,13,99,0,0,0"], &[(0, 1)]);
+        assert_eq!(outcome, Ok(Outcome::Halted));
+        assert_eq!(net.last_output(0), Some(6));
+        assert!(net.vm(0).outputs.is_empty());
+        assert_eq!(net.vm(1).outputs, [11]);
+    }
+
+    #[test]
+    fn fan_out_broadcasts() {
+        let echo = "3,5,4,5,99,0";
+        let (outcome, net) = run_both(&["104,7,99", echo, echo], &[(0, 1), (0, 2)]);
+        assert_eq!(outcome, Ok(Outcome::Halted));
+        assert_eq!(net.vm(1).outputs, [7]);
+        assert_eq!(net.vm(2).outputs, [7]);
+    }
+
+    #[test]
+    fn waiting_on_each_other_deadlocks() {
+        let (outcome, net) = run_both(&["3,0,99", "3,0,99"], &[(0, 1), (1, 0)]);
+        assert_eq!(outcome, Ok(Outcome::Deadlocked));
+        assert_eq!(net.vm(0).state, VmState::Waiting);
+        assert_eq!(net.vm(1).state, VmState::Waiting);
+    }
+
+    #[test]
+    fn unread_inputs_are_kept() {
+        // node 0 sends 1 and 2 and waits for an answer that never comes; node 1 reads only the 1
+        let (outcome, net) = run_both(&["104,1,104,2,3,0,99", "3,0,99"], &[(0, 1), (1, 0)]);
+        assert_eq!(outcome, Ok(Outcome::Deadlocked));
+        assert_eq!(net.vm(0).state, VmState::Waiting);
+        assert_eq!(net.vm(1).state, VmState::Halted);
+        assert_eq!(net.vm(1).inputs, [2]);
+    }
+
+    #[test]
+    fn fault_stops_the_network() {
+        // nodes 0 and 2 wait on node 1, which hits an unknown opcode
+        let (outcome, net) = run_both(&["3,0,99", "98", "3,0,99"], &[(1, 0), (1, 2)]);
+        let error = VmError { pc: 0, instr: 98, fault: Fault::UnknownOpcode(98) };
+        assert_eq!(outcome, Err(NetworkError { node: 1, error }));
+        assert_eq!(net.vm(0).state, VmState::Waiting);
+        assert_eq!(net.vm(2).state, VmState::Waiting);
+    }
+}
@@ -0,0 +1,109 @@
+use std::collections::VecDeque;
+use std::error::Error;
+use std::io::{self, BufRead, Write};
+use crate::memory::{Memory, MemoryKind};
+use crate::vm::{Vm, VmState};
+use crate::{tokenize, Word};
+
+const HEADER: &str = "intcode-snapshot 1";
+
+/// A frozen copy of a VM's complete state. Taking one is cheap, since memory is shared with the VM
+/// copy-on-write.
+#[derive(Debug, Clone)]
+pub struct Snapshot {
+    pub pc: usize,
+    pub rb: Word,
+    pub mem: Memory,
+    pub state: VmState,
+    pub inputs: VecDeque<Word>,
+    pub outputs: VecDeque<Word>
+}
+
+fn join<'a, I: IntoIterator<Item = &'a Word>>(words: I) -> String {
+    words.into_iter().map(|w| w.to_string()).collect::<Vec<_>>().join(",")
+}
+
+fn split(words: &str) -> Result<Vec<Word>, Box<dyn Error>> {
+    if words.is_empty() { Ok(Vec::new()) } else { Ok(tokenize(words)?) }
+}
+
+impl Snapshot {
+
+    /// Creates a new VM that starts from this snapshot and runs independently of any other.
+    pub fn fork(&self) -> Vm {
+        let mut vm = Vm::from_words(&[], &[], MemoryKind::Growable);
+        vm.restore(self);
+        vm
+    }
+
+    /// Writes the snapshot in a line-oriented text format that `load` reads back.
+    pub fn save<W: Write>(&self, mut out: W) -> io::Result<()> {
+        writeln!(out, "{}", HEADER)?;
+        writeln!(out, "pc {}", self.pc)?;
+        writeln!(out, "rb {}", self.rb)?;
+        writeln!(out, "state {:?}", self.state)?;
+        writeln!(out, "inputs {}", join(&self.inputs))?;
+        writeln!(out, "outputs {}", join(&self.outputs))?;
+        match self.mem.kind() {
+            MemoryKind::Fixed(size) => writeln!(out, "memory fixed {}", size)?,
+            MemoryKind::Growable => writeln!(out, "memory growable")?,
+            MemoryKind::Paged => writeln!(out, "memory paged")?
+        }
+        for (addr, words) in self.mem.segments() {
+            writeln!(out, "segment {} {}", addr, join(words))?;
+        }
+        out.flush()
+    }
+
+    pub fn load<R: BufRead>(input: R) -> Result<Self, Box<dyn Error>> {
+        let mut lines = input.lines();
+        if lines.next().transpose()?.as_deref() != Some(HEADER) {
+            return Err(From::from("Not an Intcode snapshot!"));
+        }
+        let mut snapshot = Snapshot {
+            pc: 0,
+            rb: 0,
+            mem: Memory::new(MemoryKind::Growable, &[]),
+            state: VmState::Ready,
+            inputs: VecDeque::new(),
+            outputs: VecDeque::new()
+        };
+        for line in lines {
+            let line = line?;
+            let (key, rest) = match line.find(' ') {
+                Some(i) => (&line[..i], line[i + 1..].trim()),
+                None => (line.as_str(), "")
+            };
+            match key {
+                "pc" => snapshot.pc = rest.parse()?,
+                "rb" => snapshot.rb = rest.parse()?,
+                "state" => snapshot.state = match rest {
+                    "Ready" => VmState::Ready,
+                    "Halted" => VmState::Halted,
+                    "Waiting" => VmState::Waiting,
+                    _ => return Err(From::from(format!("Invalid state: {}", rest)))
+                },
+                "inputs" => snapshot.inputs = split(rest)?.into_iter().collect(),
+                "outputs" => snapshot.outputs = split(rest)?.into_iter().collect(),
+                "memory" => {
+                    let kind = match rest.split_whitespace().collect::<Vec<_>>().as_slice() {
+                        ["fixed", size] => MemoryKind::Fixed(size.parse()?),
+                        ["growable"] => MemoryKind::Growable,
+                        ["paged"] => MemoryKind::Paged,
+                        _ => return Err(From::from(format!("Invalid memory kind: {}", rest)))
+                    };
+                    snapshot.mem = Memory::new(kind, &[]);
+                }
+                "segment" => {
+                    let (start, words) = rest.split_at(rest.find(' ').unwrap_or(rest.len()));
+                    let start: usize = start.parse()?;
+                    for (i, val) in split(words.trim())?.into_iter().enumerate() {
+                        snapshot.mem.write(start + i, val).map_err(|f| f.to_string())?;
+                    }
+                }
+                _ => return Err(From::from(format!("Unknown snapshot line: {}", line)))
+            }
+        }
+        Ok(snapshot)
+    }
+}
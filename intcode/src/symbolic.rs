@@ -0,0 +1,530 @@
+//! Searching for values of some memory cells that make the program leave a given value in another,
+//! day 2 style.
+//!
+//! The program is first run symbolically, with the input cells holding variables instead of numbers. If
+//! the target cell ends up a linear function of the variables, the search just solves for them.
+//! Otherwise (a multiplication of two variables, a comparison, a jump that depends on them, ...) it falls
+//! back to running the program on every combination of inputs.
+use std::convert::TryFrom;
+use std::ops::Range;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::thread;
+use crate::error::VmError;
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::memory::{MemoryKind, GROWABLE_LIMIT_WORDS};
+use crate::vm::Vm;
+use crate::Word;
+
+/// Symbolic execution gives up after this many instructions, in case the program doesn't halt.
+const STEP_LIMIT: usize = 10_000_000;
+
+/// `constant + coeffs[0] * x0 + coeffs[1] * x1 + ...`
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Linear {
+    pub constant: Word,
+    pub coeffs: Vec<Word>
+}
+
+impl Linear {
+
+    fn constant(vars: usize, constant: Word) -> Self {
+        Self { constant, coeffs: vec![0; vars] }
+    }
+
+    fn var(vars: usize, i: usize) -> Self {
+        let mut expr = Self::constant(vars, 0);
+        expr.coeffs[i] = 1;
+        expr
+    }
+
+    fn as_constant(&self) -> Option<Word> {
+        if self.coeffs.iter().all(|&c| c == 0) { Some(self.constant) } else { None }
+    }
+
+    fn add(&self, other: &Linear) -> Option<Linear> {
+        let coeffs = self.coeffs.iter().zip(&other.coeffs).map(|(a, b)| a.checked_add(*b)).collect::<Option<_>>()?;
+        Some(Linear { constant: self.constant.checked_add(other.constant)?, coeffs })
+    }
+
+    /// The product, if at least one side is a constant.
+    fn mul(&self, other: &Linear) -> Option<Linear> {
+        let (k, expr) = match (self.as_constant(), other.as_constant()) {
+            (Some(k), _) => (k, other),
+            (_, Some(k)) => (k, self),
+            _ => return None
+        };
+        let coeffs = expr.coeffs.iter().map(|c| c.checked_mul(k)).collect::<Option<_>>()?;
+        Some(Linear { constant: expr.constant.checked_mul(k)?, coeffs })
+    }
+}
+
+/// The symbolic value of a memory cell.
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub enum Expr {
+    Linear(Linear),
+    /// Anything that isn't a linear function of the inputs, such as a product of two of them, or a value
+    /// read from an address that depends on them.
+    Nonlinear
+}
+
+impl Expr {
+    fn as_constant(&self) -> Option<Word> {
+        match self {
+            Expr::Linear(expr) => expr.as_constant(),
+            Expr::Nonlinear => None
+        }
+    }
+}
+
+/// How a search found its answer.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum Method {
+    Linear,
+    BruteForce
+}
+
+#[derive(Debug, Clone, Hash, PartialEq, Eq)]
+pub struct Solution {
+    /// Values for the input cells, in order, or `None` if no combination in range works.
+    pub values: Option<Vec<Word>>,
+    pub method: Method
+}
+
+/// A search for input cell values that make the program halt with `value` in the `target` cell. When
+/// several combinations work, the first in lexicographic order wins.
+#[derive(Debug, Clone)]
+pub struct InputSearch<'a> {
+    pub program: &'a [Word],
+    /// The cells to fill in before running the program, and the values to try for each.
+    pub inputs: Vec<(usize, Range<Word>)>,
+    pub target: usize,
+    pub value: Word
+}
+
+/// Interprets a program on symbolic values. Anything the interpreter can't follow without knowing the
+/// inputs (a jump or write address that depends on them, reading input, ...) makes it give up.
+struct SymbolicVm {
+    pc: usize,
+    rb: Word,
+    mem: Vec<Expr>,
+    vars: usize
+}
+
+impl SymbolicVm {
+
+    fn addr(word: Word) -> Option<usize> {
+        if (0..GROWABLE_LIMIT_WORDS as Word).contains(&word) { Some(word as usize) } else { None }
+    }
+
+    fn constant(&self, w: Word) -> Expr {
+        Expr::Linear(Linear::constant(self.vars, w))
+    }
+
+    fn load(&self, addr: usize) -> Expr {
+        self.mem.get(addr).cloned().unwrap_or_else(|| self.constant(0))
+    }
+
+    fn store(&mut self, addr: usize, val: Expr) {
+        if addr >= self.mem.len() {
+            self.mem.resize(addr + 1, self.constant(0));
+        }
+        self.mem[addr] = val;
+    }
+
+    fn w_parm(&self, i: usize, mode: Mode) -> Option<usize> {
+        let p = self.load(self.pc + i + 1).as_constant()?;
+        match mode {
+            Mode::Position => SymbolicVm::addr(p),
+            Mode::Immediate => None,
+            Mode::Relative => SymbolicVm::addr(p.checked_add(self.rb)?)
+        }
+    }
+
+    fn r_parm(&self, i: usize, mode: Mode) -> Option<Expr> {
+        let p = self.load(self.pc + i + 1);
+        let base = match mode {
+            Mode::Position => 0,
+            Mode::Immediate => return Some(p),
+            Mode::Relative => self.rb
+        };
+        match p.as_constant() {
+            Some(p) => Some(self.load(SymbolicVm::addr(p.checked_add(base)?)?)),
+            None => Some(Expr::Nonlinear)
+        }
+    }
+
+    /// Runs to completion, returning the final memory, or `None` if the interpreter had to give up.
+    fn run(mut self) -> Option<Vec<Expr>> {
+        for _ in 0..STEP_LIMIT {
+            let Instruction { opcode, modes } = Instruction::decode(self.load(self.pc).as_constant()?).ok()?;
+            match opcode {
+                Opcode::Add | Opcode::Mul | Opcode::Lt | Opcode::Eq => {
+                    let a = self.r_parm(0, modes[0])?;
+                    let b = self.r_parm(1, modes[1])?;
+                    let c = self.w_parm(2, modes[2])?;
+                    let result = match (opcode, &a, &b) {
+                        (Opcode::Add, Expr::Linear(a), Expr::Linear(b)) => a.add(b).map(Expr::Linear),
+                        (Opcode::Mul, Expr::Linear(a), Expr::Linear(b)) => a.mul(b).map(Expr::Linear),
+                        _ => match (opcode, a.as_constant(), b.as_constant()) {
+                            (Opcode::Lt, Some(a), Some(b)) => Some(self.constant((a < b) as Word)),
+                            (Opcode::Eq, Some(a), Some(b)) => Some(self.constant((a == b) as Word)),
+                            _ => None
+                        }
+                    };
+                    self.store(c, result.unwrap_or(Expr::Nonlinear));
+                    self.pc += 4;
+                }
+                Opcode::In => return None,
+                Opcode::Out => {
+                    self.r_parm(0, modes[0])?;
+                    self.pc += 2;
+                }
+                Opcode::Jt | Opcode::Jf => {
+                    let a = self.r_parm(0, modes[0])?.as_constant()?;
+                    if (a != 0) == (opcode == Opcode::Jt) {
+                        self.pc = SymbolicVm::addr(self.r_parm(1, modes[1])?.as_constant()?)?;
+                    } else {
+                        self.pc += 3;
+                    }
+                }
+                Opcode::Arb => {
+                    self.rb = self.rb.checked_add(self.r_parm(0, modes[0])?.as_constant()?)?;
+                    self.pc += 2;
+                }
+                Opcode::Hlt => return Some(self.mem)
+            }
+        }
+        None
+    }
+}
+
+/// The `index`th combination of values from `ranges`, in lexicographic order.
+fn nth_combination(ranges: &[Range<Word>], mut index: usize) -> Vec<Word> {
+    let mut values = vec![0; ranges.len()];
+    for (value, range) in values.iter_mut().zip(ranges).rev() {
+        let len = range_len(range);
+        *value = (range.start as i128 + (index % len) as i128) as Word;
+        index /= len;
+    }
+    values
+}
+
+/// `(g, s, t)` with `g` the (non-negative) greatest common divisor of `a` and `b`, and `a * s + b * t == g`.
+fn ext_gcd(a: i128, b: i128) -> (i128, i128, i128) {
+    if b == 0 {
+        (a.abs(), a.signum(), 0)
+    } else {
+        let (g, s, t) = ext_gcd(b, a % b);
+        (g, t, s - (a / b) * t)
+    }
+}
+
+/// The solution of `a * x + b * y == n` with `x` in `xs`, `y` in `ys` and the smallest `x`, if there is
+/// one. `a` and `b` must be nonzero. Gives up (returning `None`) if the numbers involved get too big for
+/// an `i128`, which only happens when `n` is already far out of reach.
+fn first_solution(a: i128, b: i128, n: i128, xs: &Range<Word>, ys: &Range<Word>) -> Option<(Word, Word)> {
+    let (g, s, _) = ext_gcd(a, b);
+    if n % g != 0 {
+        return None;
+    }
+    let (a, b, n) = (a / g, b / g, n / g);
+    // the solutions are x = x0 + m * u, y = y0 + dy * u for any integer u
+    let m = b.abs();
+    let x0 = ((s.rem_euclid(m) as u128 * n.rem_euclid(m) as u128) % m as u128) as i128;
+    let y0 = n.checked_sub(a * x0)? / b;
+    let dy = -a * b.signum();
+    let floor = |p: i128, q: i128| p.div_euclid(q);
+    let ceil = |p: i128, q: i128| -(-p).div_euclid(q);
+    let (xlo, xhi) = (xs.start as i128, xs.end as i128 - 1);
+    let (ylo, yhi) = (ys.start as i128, ys.end as i128 - 1);
+    let (ulo_y, uhi_y) = if dy > 0 {
+        (ceil(ylo.checked_sub(y0)?, dy), floor(yhi.checked_sub(y0)?, dy))
+    } else {
+        (ceil(y0.checked_sub(yhi)?, -dy), floor(y0.checked_sub(ylo)?, -dy))
+    };
+    let u = ceil(xlo - x0, m).max(ulo_y);
+    if u > floor(xhi - x0, m).min(uhi_y) {
+        return None;
+    }
+    Some((Word::try_from(x0 + m * u).ok()?, Word::try_from(y0.checked_add(dy.checked_mul(u)?)?).ok()?))
+}
+
+/// How many values `range` holds, which can be more than a `Word` can count.
+fn range_len(range: &Range<Word>) -> usize {
+    usize::try_from((range.end as i128 - range.start as i128).max(0)).unwrap_or(usize::MAX)
+}
+
+fn combinations(ranges: &[Range<Word>]) -> usize {
+    ranges.iter().map(range_len).fold(1, usize::saturating_mul)
+}
+
+impl InputSearch<'_> {
+
+    fn ranges(&self) -> Vec<Range<Word>> {
+        self.inputs.iter().map(|(_, range)| range.clone()).collect()
+    }
+
+    /// The target cell's final value as a function of the input cells, or `None` if the program's
+    /// behavior can't be worked out without knowing the inputs.
+    pub fn symbolic(&self) -> Option<Expr> {
+        let vars = self.inputs.len();
+        let mut vm = SymbolicVm {
+            pc: 0,
+            rb: 0,
+            mem: self.program.iter().map(|&w| Expr::Linear(Linear::constant(vars, w))).collect(),
+            vars
+        };
+        for (i, &(cell, _)) in self.inputs.iter().enumerate() {
+            vm.store(cell, Expr::Linear(Linear::var(vars, i)));
+        }
+        let zero = vm.constant(0);
+        vm.run().map(|mem| mem.get(self.target).cloned().unwrap_or(zero))
+    }
+
+    /// Solves `expr == value` for the inputs. Inputs that don't matter are left at the start of their
+    /// ranges; the last two that do are solved for directly, and any others are enumerated.
+    fn solve_linear(&self, expr: &Linear) -> Option<Vec<Word>> {
+        let ranges = self.ranges();
+        if ranges.iter().any(|r| r.is_empty()) {
+            return None;
+        }
+        let mut values: Vec<_> = ranges.iter().map(|r| r.start).collect();
+        let used: Vec<_> = (0..values.len()).filter(|&i| expr.coeffs[i] != 0).collect();
+        let (k, rest) = match used.split_last() {
+            Some((&k, rest)) => (k, rest),
+            None => return if expr.constant == self.value { Some(values) } else { None }
+        };
+        let (j, enumerated) = match rest.split_last() {
+            Some((&j, enumerated)) => (Some(j), enumerated),
+            None => (None, rest)
+        };
+        let enumerated_ranges: Vec<_> = enumerated.iter().map(|&i| ranges[i].clone()).collect();
+        let coeff = |i: usize| expr.coeffs[i] as i128;
+        for index in 0..combinations(&enumerated_ranges) {
+            let combination = nth_combination(&enumerated_ranges, index);
+            // if this overflows, the last inputs would have to be far too big to fit in a word anyway
+            let sum = enumerated.iter().zip(&combination)
+                .try_fold(0i128, |sum, (&i, &v)| sum.checked_add(coeff(i) * v as i128));
+            let needed = match sum {
+                Some(sum) => self.value as i128 - expr.constant as i128 - sum,
+                None => continue
+            };
+            let solution = match j {
+                Some(j) => first_solution(coeff(j), coeff(k), needed, &ranges[j], &ranges[k]).map(|(x, y)| (Some(x), y)),
+                None if needed % coeff(k) == 0 => {
+                    Word::try_from(needed / coeff(k)).ok().filter(|y| ranges[k].contains(y)).map(|y| (None, y))
+                }
+                None => None
+            };
+            if let Some((x, y)) = solution {
+                for (&i, &v) in enumerated.iter().zip(&combination) {
+                    values[i] = v;
+                }
+                if let (Some(j), Some(x)) = (j, x) {
+                    values[j] = x;
+                }
+                values[k] = y;
+                return Some(values);
+            }
+        }
+        None
+    }
+
+    /// Whether a real run with the given inputs ends with `value` in the target cell.
+    fn check(&self, values: &[Word]) -> Result<bool, VmError> {
+        let mut vm = Vm::from_words(self.program, &[], MemoryKind::Growable);
+        for (&(cell, _), &value) in self.inputs.iter().zip(values) {
+            vm.mem.write(cell, value).map_err(|fault| VmError { pc: 0, instr: 0, fault })?;
+        }
+        vm.run()?;
+        Ok(vm.mem.read(self.target) == Ok(self.value))
+    }
+
+    /// Runs the program on every combination of inputs, split across `threads` threads.
+    pub fn brute_force(&self, threads: usize) -> Result<Option<Vec<Word>>, VmError> {
+        let ranges = self.ranges();
+        let total = combinations(&ranges);
+        let chunk_size = total.div_ceil(threads.max(1)).max(1);
+        let found = AtomicUsize::new(usize::MAX);
+        let errors: Vec<(usize, VmError)> = thread::scope(|s| {
+            let handles: Vec<_> = (0..total).step_by(chunk_size).map(|start| {
+                let (ranges, found) = (&ranges, &found);
+                s.spawn(move || -> Result<(), (usize, VmError)> {
+                    let mut vm = Vm::from_words(self.program, &[], MemoryKind::Growable);
+                    let initial = vm.snapshot();
+                    for index in start..total.min(start + chunk_size) {
+                        if index > found.load(Ordering::Relaxed) {
+                            break;
+                        }
+                        vm.restore(&initial);
+                        for (&(cell, _), value) in self.inputs.iter().zip(nth_combination(ranges, index)) {
+                            vm.mem.write(cell, value).map_err(|fault| (index, VmError { pc: 0, instr: 0, fault }))?;
+                        }
+                        vm.run().map_err(|e| (index, e))?;
+                        if vm.mem.read(self.target) == Ok(self.value) {
+                            found.fetch_min(index, Ordering::Relaxed);
+                            break;
+                        }
+                    }
+                    Ok(())
+                })
+            }).collect();
+            handles.into_iter().filter_map(|handle| handle.join().unwrap().err()).collect()
+        });
+        let found = found.into_inner();
+        if let Some(&(_, error)) = errors.iter().filter(|&&(index, _)| index < found).min_by_key(|&&(index, _)| index) {
+            return Err(error);
+        }
+        Ok(if found == usize::MAX { None } else { Some(nth_combination(&ranges, found)) })
+    }
+
+    /// Solves directly if the target is a linear function of the inputs, and falls back to `brute_force`
+    /// otherwise. A direct solution is double-checked with a real run, and the search falls back to brute
+    /// force if that disagrees or faults.
+    pub fn solve(&self, threads: usize) -> Result<Solution, VmError> {
+        if let Some(Expr::Linear(expr)) = self.symbolic() {
+            match self.solve_linear(&expr) {
+                Some(values) if self.check(&values) == Ok(true) => {
+                    return Ok(Solution { values: Some(values), method: Method::Linear });
+                }
+                None => return Ok(Solution { values: None, method: Method::Linear }),
+                _ => {}
+            }
+        }
+        Ok(Solution { values: self.brute_force(threads)?, method: Method::BruteForce })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::Fault;
+    use crate::tokenize;
+
+    /// `program` with the cells at 20 and 21 as inputs, each tried from 0 to 9, looking for `value` in
+    /// cell 0.
+    fn search(program: &[Word], value: Word) -> InputSearch<'_> {
+        InputSearch { program, inputs: vec![(20, 0..10), (21, 0..10)], target: 0, value }
+    }
+
+    /// Pads `program` out past the input cells.
+    fn padded(program: &[Word]) -> Vec<Word> {
+        let mut words = program.to_vec();
+        words.resize(22, 0);
+        words
+    }
+
+    #[test]
+    fn solves_day2_directly() {
+        let program = tokenize(include_str!("../../day2/input").trim_end()).unwrap();
+        let search = InputSearch { program: &program, inputs: vec![(1, 0..100), (2, 0..100)], target: 0, value: 19690720 };
+        assert!(matches!(search.symbolic(), Some(Expr::Linear(_))));
+        assert_eq!(search.solve(2), Ok(Solution { values: Some(vec![41, 12]), method: Method::Linear }));
+        assert_eq!(search.brute_force(2), Ok(Some(vec![41, 12])));
+    }
+
+    #[test]
+    fn linear_solution_is_the_first_in_order() {
+        // cell 0 = a + b, which is 5 for (0, 5), (1, 4), ...
+        let program = padded(&[1, 20, 21, 0, 99]);
+        let search = search(&program, 5);
+        assert_eq!(search.solve(1), Ok(Solution { values: Some(vec![0, 5]), method: Method::Linear }));
+        for threads in 1..5 {
+            assert_eq!(search.brute_force(threads), Ok(Some(vec![0, 5])));
+        }
+    }
+
+    #[test]
+    fn nonlinear_falls_back_to_brute_force() {
+        // cell 0 = a * b, which is 12 for (2, 6), (3, 4), ...
+        let program = padded(&[2, 20, 21, 0, 99]);
+        let search = search(&program, 12);
+        assert_eq!(search.symbolic(), Some(Expr::Nonlinear));
+        for threads in 1..5 {
+            assert_eq!(search.solve(threads), Ok(Solution { values: Some(vec![2, 6]), method: Method::BruteForce }));
+        }
+    }
+
+    #[test]
+    fn no_solution() {
+        let program = padded(&[1, 20, 21, 0, 99]);
+        assert_eq!(search(&program, 100).solve(1), Ok(Solution { values: None, method: Method::Linear }));
+        let program = padded(&[2, 20, 21, 0, 99]);
+        assert_eq!(search(&program, 100).solve(3), Ok(Solution { values: None, method: Method::BruteForce }));
+    }
+
+    #[test]
+    fn empty_ranges() {
+        let program = padded(&[1, 20, 21, 0, 99]);
+        let search = InputSearch { program: &program, inputs: vec![(20, 0..10), (21, 5..5)], target: 0, value: 5 };
+        assert_eq!(search.solve(1), Ok(Solution { values: None, method: Method::Linear }));
+        assert_eq!(search.brute_force(2), Ok(None));
+    }
+
+    #[test]
+    fn huge_ranges() {
+        let all = Word::MIN..Word::MAX;
+        assert_eq!(range_len(&all), usize::MAX);
+        assert_eq!(range_len(&Range { start: 5, end: -5 }), 0);
+        assert_eq!(nth_combination(&[all.clone(), 0..10], 19), [Word::MIN + 1, 9]);
+        assert_eq!(nth_combination(&[all.clone(), 0..1], usize::MAX - 1), [Word::MAX - 1, 0]);
+        let wide = -5_000_000_000_000_000_000..5_000_000_000_000_000_000;
+        assert_eq!(combinations(&[wide.clone(), 0..1]), 10_000_000_000_000_000_000);
+        assert_eq!(combinations(&[wide.clone(), wide]), usize::MAX);
+
+        // cell 0 = a + b, where a would have to be past Word::MAX for the lowest few values of b
+        let program = padded(&[1, 20, 21, 0, 99]);
+        let search = InputSearch { program: &program, inputs: vec![(21, all.clone()), (20, all)], target: 0, value: 5 };
+        assert_eq!(search.solve(1), Ok(Solution { values: Some(vec![Word::MIN + 7, Word::MAX - 1]), method: Method::Linear }));
+    }
+
+    #[test]
+    fn linear_solutions_match_brute_force() {
+        // cell 0 = 6a + 10b + 1, which only hits odd values, and not all of those
+        let program = padded(&[1002, 20, 6, 18, 1002, 21, 10, 19, 1, 18, 19, 0, 1001, 0, 1, 0, 99]);
+        for value in -150..150 {
+            let search = InputSearch { program: &program, inputs: vec![(20, -20..20), (21, -20..20)], target: 0, value };
+            let solution = search.solve(1).unwrap();
+            assert_eq!(solution.method, Method::Linear);
+            assert_eq!(solution.values, search.brute_force(1).unwrap(), "value {}", value);
+        }
+        // cell 0 = c - 3a + 2b, with the inputs in every order
+        let program = padded(&[1002, 19, -3, 17, 1002, 20, 2, 18, 1, 17, 18, 0, 1, 0, 21, 0, 99]);
+        for value in -40..40 {
+            for inputs in [[19, 20, 21], [21, 19, 20], [20, 21, 19]].iter() {
+                let inputs = inputs.iter().map(|&cell| (cell, -5..5)).collect();
+                let search = InputSearch { program: &program, inputs, target: 0, value };
+                assert_eq!(search.solve(1).unwrap().values, search.brute_force(1).unwrap(), "value {}", value);
+            }
+        }
+    }
+
+    #[test]
+    fn solves_day2_over_huge_ranges() {
+        let program = tokenize(include_str!("../../day2/input").trim_end()).unwrap();
+        let wide = -5_000_000_000_000_000_000..5_000_000_000_000_000_000;
+        let search = InputSearch { program: &program, inputs: vec![(1, wide.clone()), (2, wide)], target: 0, value: 19690720 };
+        let expr = match search.symbolic() {
+            Some(Expr::Linear(expr)) => expr,
+            other => panic!("{:?}", other)
+        };
+        // the smallest noun is the one that needs a verb just under the top of its range
+        assert!(expr.coeffs[0] > 0 && expr.coeffs[1] == 1);
+        let values = search.solve_linear(&expr).unwrap();
+        assert_eq!(expr.constant + expr.coeffs[0] * values[0] + values[1], 19690720);
+        assert!(values[0] < 0);
+        assert!(values[1] < search.inputs[1].1.end && values[1] + expr.coeffs[0] >= search.inputs[1].1.end);
+    }
+
+    #[test]
+    fn faulting_linear_solution_falls_back() {
+        // cell 0 = (a + BIG) + b - BIG, which is linear, but overflows for the solution (0, 5)
+        let big = Word::MAX - 3;
+        let program = padded(&[1001, 20, big, 18, 1, 18, 21, 19, 1001, 19, -big, 0, 99]);
+        let search = search(&program, 5);
+        assert!(matches!(search.symbolic(), Some(Expr::Linear(_))));
+        let error = search.brute_force(1).unwrap_err();
+        assert_eq!(error.fault, Fault::Overflow);
+        assert_eq!(search.solve(1), Err(error));
+    }
+}
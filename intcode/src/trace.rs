@@ -0,0 +1,112 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::io::{self, Write};
+use crate::instruction::Opcode;
+use crate::vm::Step;
+
+/// Receives every instruction a VM executes during `Vm::run_traced`.
+pub trait Tracer {
+    fn trace(&mut self, step: &Step);
+}
+
+impl<A: Tracer, B: Tracer> Tracer for (A, B) {
+    fn trace(&mut self, step: &Step) {
+        self.0.trace(step);
+        self.1.trace(step);
+    }
+}
+
+/// Writes one line per executed instruction:
+///
+/// ```text
+/// <pc> <mnemonic> <operands> <write>
+/// ```
+///
+/// where the operands are the resolved values (or addresses, for write parameters) separated by commas,
+/// and the write is `[addr]=value`, or `-` if nothing was written.
+pub struct TraceWriter<W: Write> {
+    out: W,
+    error: Option<io::Error>
+}
+
+impl<W: Write> TraceWriter<W> {
+
+    pub fn new(out: W) -> Self {
+        Self { out, error: None }
+    }
+
+    /// Flushes the output and reports the first write error, if any happened while tracing.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error.take() {
+            return Err(e);
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+impl<W: Write> Tracer for TraceWriter<W> {
+    fn trace(&mut self, step: &Step) {
+        if self.error.is_some() {
+            return;
+        }
+        let operands: Vec<_> = step.operands[..step.instr.opcode.arity()].iter().map(|w| w.to_string()).collect();
+        let operands = if operands.is_empty() { "-".to_owned() } else { operands.join(",") };
+        let write = match step.write {
+            Some((addr, val)) => format!("[{}]={}", addr, val),
+            None => "-".to_owned()
+        };
+        let result = writeln!(self.out, "{} {} {} {}", step.pc, step.instr.opcode.mnemonic(), operands, write);
+        if let Err(e) = result {
+            self.error = Some(e);
+        }
+    }
+}
+
+/// Counts executed instructions per opcode and per address.
+#[derive(Debug, Clone, Default)]
+pub struct Profiler {
+    pub cycles: u64,
+    pub by_opcode: HashMap<Opcode, u64>,
+    pub by_pc: HashMap<usize, u64>
+}
+
+impl Profiler {
+
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The `n` most executed addresses, most executed first.
+    pub fn hottest(&self, n: usize) -> Vec<(usize, u64)> {
+        let mut pcs: Vec<_> = self.by_pc.iter().map(|(&pc, &count)| (pc, count)).collect();
+        pcs.sort_by(|a, b| b.1.cmp(&a.1).then(a.0.cmp(&b.0)));
+        pcs.truncate(n);
+        pcs
+    }
+}
+
+impl Tracer for Profiler {
+    fn trace(&mut self, step: &Step) {
+        self.cycles += 1;
+        *self.by_opcode.entry(step.instr.opcode).or_insert(0) += 1;
+        *self.by_pc.entry(step.pc).or_insert(0) += 1;
+    }
+}
+
+impl fmt::Display for Profiler {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Total cycles: {}", self.cycles)?;
+        writeln!(f, "By opcode:")?;
+        let mut ops: Vec<_> = self.by_opcode.iter().collect();
+        ops.sort_by(|a, b| b.1.cmp(a.1));
+        for (op, count) in ops {
+            writeln!(f, "  {:<4} {:>12}", op.mnemonic(), count)?;
+        }
+        writeln!(f, "Hottest addresses:")?;
+        for (pc, count) in self.hottest(10) {
+            writeln!(f, "  {:>5} {:>12}", pc, count)?;
+        }
+        Ok(())
+    }
+}
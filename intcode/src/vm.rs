@@ -0,0 +1,333 @@
+use std::collections::VecDeque;
+use std::mem;
+use std::num::ParseIntError;
+use std::sync::OnceLock;
+use crate::error::{Fault, VmError};
+use crate::instruction::{Instruction, Mode, Opcode};
+use crate::io::{Io, Queues};
+use crate::memory::{Memory, MemoryKind};
+use crate::snapshot::Snapshot;
+use crate::trace::Tracer;
+use crate::{tokenize, Word};
+
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub enum VmState {
+    Ready,
+    Halted,
+    Waiting
+}
+
+/// What a single executed instruction did.
+#[derive(Debug, Copy, Clone, Hash, PartialEq, Eq)]
+pub struct Step {
+    pub pc: usize,
+    pub instr: Instruction,
+    /// Resolved parameters: values for read parameters, addresses for write parameters. Entries past the
+    /// opcode's arity are 0.
+    pub operands: [Word; 3],
+    /// Address and value written to memory, if any.
+    pub write: Option<(usize, Word)>,
+    pub output: Option<Word>
+}
+
+/// Every word that decodes to a valid instruction is below this (the largest being 22299: all three
+/// parameters relative, then opcode 99).
+const DECODE_TABLE_SIZE: usize = 22300;
+
+/// The decoded form of every valid instruction word, shared by all VMs and built the first time one
+/// needs it. Decoding depends only on the word, so looking instructions up by word rather than address
+/// means nothing ever needs invalidating, however the program (or anyone writing to `mem`) rewrites it.
+fn decode_table() -> &'static [Option<Instruction>] {
+    static TABLE: OnceLock<Vec<Option<Instruction>>> = OnceLock::new();
+    TABLE.get_or_init(|| (0..DECODE_TABLE_SIZE as Word).map(|w| Instruction::decode(w).ok()).collect())
+}
+
+#[derive(Debug)]
+pub struct Vm {
+    pub pc: usize,
+    pub rb: Word,
+    pub mem: Memory,
+    pub state: VmState,
+    pub inputs: VecDeque<Word>,
+    pub outputs: VecDeque<Word>,
+    /// Whether to look instructions up in the shared decode table rather than decoding them every time.
+    decode_cache: bool
+}
+
+impl Vm {
+
+    /// Parses a comma-separated program and loads it into a memory of the given kind.
+    pub fn create(program: &str, initial_inputs: &[Word], mem_kind: MemoryKind) -> Result<Self, ParseIntError> {
+        let contents = tokenize(program.trim_end())?;
+        Ok(Self::from_words(&contents, initial_inputs, mem_kind))
+    }
+
+    /// Loads an already-tokenized program into a memory of the given kind.
+    pub fn from_words(program: &[Word], initial_inputs: &[Word], mem_kind: MemoryKind) -> Self {
+        let mem = Memory::new(mem_kind, program);
+        let mut inputs = VecDeque::new();
+        inputs.extend(initial_inputs);
+        Self {
+            mem,
+            inputs,
+            outputs: VecDeque::new(),
+            pc: 0,
+            rb: 0,
+            state: VmState::Ready,
+            decode_cache: true
+        }
+    }
+
+    /// Turns the decoded instruction cache on or off. It's on by default; turning it off is mostly useful
+    /// for measuring what it's worth.
+    pub fn set_decode_cache(&mut self, enabled: bool) {
+        self.decode_cache = enabled;
+    }
+
+    /// Captures the VM's complete state. Memory is shared copy-on-write, so this is cheap.
+    pub fn snapshot(&self) -> Snapshot {
+        Snapshot {
+            pc: self.pc,
+            rb: self.rb,
+            mem: self.mem.clone(),
+            state: self.state,
+            inputs: self.inputs.clone(),
+            outputs: self.outputs.clone()
+        }
+    }
+
+    /// Puts the VM back into the state captured by `snapshot`.
+    pub fn restore(&mut self, snapshot: &Snapshot) {
+        self.pc = snapshot.pc;
+        self.rb = snapshot.rb;
+        self.mem = snapshot.mem.clone();
+        self.state = snapshot.state;
+        self.inputs = snapshot.inputs.clone();
+        self.outputs = snapshot.outputs.clone();
+    }
+
+    fn addr(word: Word) -> Result<usize, Fault> {
+        if word < 0 { Err(Fault::NegativeAddress(word)) } else { Ok(word as usize) }
+    }
+
+    fn load(&self, addr: usize) -> Result<Word, Fault> {
+        self.mem.read(addr)
+    }
+
+    fn store(&mut self, addr: usize, val: Word) -> Result<(), Fault> {
+        self.mem.write(addr, val)
+    }
+
+    fn decode(&self, instr: Word) -> Result<Instruction, Fault> {
+        if self.decode_cache {
+            // negative words wrap around to huge indices, and miss like any other invalid word
+            if let Some(&Some(decoded)) = decode_table().get(instr as usize) {
+                return Ok(decoded);
+            }
+        }
+        Instruction::decode(instr)
+    }
+
+    fn w_parm(&self, p: Word, mode: Mode) -> Result<usize, Fault> {
+        match mode {
+            Mode::Position => Vm::addr(p),
+            Mode::Immediate => Err(Fault::WriteInImmediateMode),
+            Mode::Relative => Vm::addr(p.checked_add(self.rb).ok_or(Fault::Overflow)?)
+        }
+    }
+
+    fn r_parm(&self, p: Word, mode: Mode) -> Result<Word, Fault> {
+        match mode {
+            Mode::Position => self.load(Vm::addr(p)?),
+            Mode::Immediate => Ok(p),
+            Mode::Relative => self.load(Vm::addr(p.checked_add(self.rb).ok_or(Fault::Overflow)?)?)
+        }
+    }
+
+    /// Lends the VM's own `inputs` and `outputs` to `f` as a `Queues`, putting them back afterwards.
+    fn with_queues<T>(&mut self, f: impl FnOnce(&mut Self, &mut Queues) -> T) -> T {
+        let mut io = Queues { inputs: mem::take(&mut self.inputs), outputs: mem::take(&mut self.outputs) };
+        let result = f(self, &mut io);
+        self.inputs = io.inputs;
+        self.outputs = io.outputs;
+        result
+    }
+
+    /// Runs until the program halts or needs an input that isn't queued yet.
+    pub fn run(&mut self) -> Result<(), VmError> {
+        self.with_queues(|vm, io| vm.run_with(io))
+    }
+
+    /// Runs until the program halts or `io` has no input to give it.
+    pub fn run_with(&mut self, io: &mut dyn Io) -> Result<(), VmError> {
+        while self.step_with(io)?.is_some() && self.state == VmState::Ready {}
+        Ok(())
+    }
+
+    /// Like `run`, but hands every executed instruction to `tracer`.
+    pub fn run_traced(&mut self, tracer: &mut dyn Tracer) -> Result<(), VmError> {
+        self.with_queues(|vm, io| {
+            while let Some(step) = vm.step_with(io)? {
+                tracer.trace(&step);
+                if vm.state != VmState::Ready {
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Executes exactly one instruction and reports what it did. Returns `None` without executing
+    /// anything if the VM has halted or the next instruction needs an input that isn't queued yet.
+    pub fn step(&mut self) -> Result<Option<Step>, VmError> {
+        self.with_queues(|vm, io| vm.step_with(io))
+    }
+
+    /// Like `step`, but reads inputs from and writes outputs to `io` instead of the VM's own queues.
+    pub fn step_with(&mut self, io: &mut dyn Io) -> Result<Option<Step>, VmError> {
+        if self.state == VmState::Halted {
+            return Ok(None);
+        }
+        let pc = self.pc;
+        let instr = self.load(pc).map_err(|fault| VmError { pc, instr: 0, fault })?;
+        self.decode(instr)
+            .and_then(|decoded| self.exec(decoded, io))
+            .map_err(|fault| VmError { pc, instr, fault })
+    }
+
+    fn exec(&mut self, decoded: Instruction, io: &mut dyn Io) -> Result<Option<Step>, Fault> {
+        let Instruction { opcode, modes } = decoded;
+        let mut parms = [0; 3];
+        self.mem.read_into(self.pc + 1, &mut parms[..opcode.arity()])?;
+        let mut step = Step { pc: self.pc, instr: decoded, operands: [0; 3], write: None, output: None };
+        let mut next_pc = self.pc;
+        self.state = VmState::Ready;
+        match opcode {
+            Opcode::Add => {
+                let a = self.r_parm(parms[0], modes[0])?;
+                let b = self.r_parm(parms[1], modes[1])?;
+                let c = self.w_parm(parms[2], modes[2])?;
+                step.operands = [a, b, c as Word];
+                step.write = Some((c, a.checked_add(b).ok_or(Fault::Overflow)?));
+                next_pc += 4;
+            }
+            Opcode::Mul => {
+                let a = self.r_parm(parms[0], modes[0])?;
+                let b = self.r_parm(parms[1], modes[1])?;
+                let c = self.w_parm(parms[2], modes[2])?;
+                step.operands = [a, b, c as Word];
+                step.write = Some((c, a.checked_mul(b).ok_or(Fault::Overflow)?));
+                next_pc += 4;
+            }
+            Opcode::In => {
+                let dest = self.w_parm(parms[0], modes[0])?;
+                // don't take an input we'd then have nowhere to put
+                self.mem.check_write(dest)?;
+                if let Some(n) = io.input() {
+                    step.operands[0] = dest as Word;
+                    step.write = Some((dest, n));
+                    next_pc += 2;
+                } else {
+                    self.state = VmState::Waiting;
+                    return Ok(None);
+                }
+            }
+            Opcode::Out => {
+                let a = self.r_parm(parms[0], modes[0])?;
+                step.operands[0] = a;
+                step.output = Some(a);
+                io.output(a);
+                next_pc += 2;
+            }
+            Opcode::Jt => {
+                let a = self.r_parm(parms[0], modes[0])?;
+                let b = self.r_parm(parms[1], modes[1])?;
+                step.operands = [a, b, 0];
+                next_pc = if a != 0 { Vm::addr(b)? } else { self.pc + 3 };
+            }
+            Opcode::Jf => {
+                let a = self.r_parm(parms[0], modes[0])?;
+                let b = self.r_parm(parms[1], modes[1])?;
+                step.operands = [a, b, 0];
+                next_pc = if a == 0 { Vm::addr(b)? } else { self.pc + 3 };
+            }
+            Opcode::Lt => {
+                let a = self.r_parm(parms[0], modes[0])?;
+                let b = self.r_parm(parms[1], modes[1])?;
+                let c = self.w_parm(parms[2], modes[2])?;
+                step.operands = [a, b, c as Word];
+                step.write = Some((c, if a < b { 1 } else { 0 }));
+                next_pc += 4;
+            }
+            Opcode::Eq => {
+                let a = self.r_parm(parms[0], modes[0])?;
+                let b = self.r_parm(parms[1], modes[1])?;
+                let c = self.w_parm(parms[2], modes[2])?;
+                step.operands = [a, b, c as Word];
+                step.write = Some((c, if a == b { 1 } else { 0 }));
+                next_pc += 4;
+            }
+            Opcode::Arb => {
+                let a = self.r_parm(parms[0], modes[0])?;
+                step.operands[0] = a;
+                self.rb = self.rb.checked_add(a).ok_or(Fault::Overflow)?;
+                next_pc += 2;
+            }
+            Opcode::Hlt => {
+                self.state = VmState::Halted;
+            }
+        }
+        if let Some((addr, val)) = step.write {
+            self.store(addr, val)?;
+        }
+        self.pc = next_pc;
+        Ok(Some(step))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fault(program: &str) -> Fault {
+        let mut vm = Vm::create(program, &[], MemoryKind::Growable).unwrap();
+        vm.run().unwrap_err().fault
+    }
+
+    #[test]
+    fn overflowing_arithmetic_faults() {
+        assert_eq!(fault("1101,9223372036854775807,1,0,99"), Fault::Overflow);
+        assert_eq!(fault("1102,9223372036854775807,2,0,99"), Fault::Overflow);
+    }
+
+    #[test]
+    fn overflowing_relative_base_faults() {
+        assert_eq!(fault("109,9223372036854775807,22201,1,1,0,99"), Fault::Overflow);
+        assert_eq!(fault("109,9223372036854775807,109,1,99"), Fault::Overflow);
+    }
+
+    #[test]
+    fn decode_table_agrees_with_decode() {
+        let vm = Vm::from_words(&[], &[], MemoryKind::Growable);
+        for word in -10..DECODE_TABLE_SIZE as Word + 10 {
+            assert_eq!(vm.decode(word), Instruction::decode(word), "word {}", word);
+        }
+    }
+
+    #[test]
+    fn rewritten_code_runs_as_rewritten() {
+        // the first pass outputs 7 and turns the output into a halt, so the loop only goes round once
+        let program = "4,9,1101,0,99,0,1105,1,0,7";
+        let mut vm = Vm::create(program, &[], MemoryKind::Growable).unwrap();
+        vm.run().unwrap();
+        assert_eq!(vm.outputs, [7]);
+        assert_eq!(vm.state, VmState::Halted);
+    }
+
+    #[test]
+    fn failed_input_keeps_the_value() {
+        let mut vm = Vm::create("3,10,99", &[7], MemoryKind::Fixed(3)).unwrap();
+        assert_eq!(vm.run().unwrap_err().fault, Fault::AddressOutOfRange(10));
+        assert_eq!(vm.inputs, [7]);
+    }
+}
@@ -0,0 +1,149 @@
+//! Reads the block letters that puzzles draw, in the usual font that's six pixels tall.
+
+/// The height of every letter, in pixels.
+pub const HEIGHT: usize = 6;
+
+/// The letters we know how to read. `#` is a lit pixel. Most are four pixels wide, but not all.
+const GLYPHS: &[(char, [&str; HEIGHT])] = &[
+    ('A', [".##.", "#..#", "#..#", "####", "#..#", "#..#"]),
+    ('B', ["###.", "#..#", "###.", "#..#", "#..#", "###."]),
+    ('C', [".##.", "#..#", "#...", "#...", "#..#", ".##."]),
+    ('E', ["####", "#...", "###.", "#...", "#...", "####"]),
+    ('F', ["####", "#...", "###.", "#...", "#...", "#..."]),
+    ('G', [".##.", "#..#", "#...", "#.##", "#..#", ".###"]),
+    ('H', ["#..#", "#..#", "####", "#..#", "#..#", "#..#"]),
+    ('I', [".###", "..#.", "..#.", "..#.", "..#.", ".###"]),
+    ('J', ["..##", "...#", "...#", "...#", "#..#", ".##."]),
+    ('K', ["#..#", "#.#.", "##..", "#.#.", "#.#.", "#..#"]),
+    ('L', ["#...", "#...", "#...", "#...", "#...", "####"]),
+    ('O', [".##.", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('P', ["###.", "#..#", "#..#", "###.", "#...", "#..."]),
+    ('R', ["###.", "#..#", "#..#", "###.", "#.#.", "#..#"]),
+    ('S', [".###", "#...", "#...", ".##.", "...#", "###."]),
+    ('U', ["#..#", "#..#", "#..#", "#..#", "#..#", ".##."]),
+    ('Y', ["#...#", "#...#", ".#.#.", "..#..", "..#..", "..#.."]),
+    ('Z', ["####", "...#", "..#.", ".#..", "#...", "####"])
+];
+
+/// How far apart letters are: four columns wide plus one blank column between them. A five-column letter
+/// like `Y` runs right up against the next one.
+pub const PITCH: usize = 5;
+
+fn is_lit(pattern: &[&str; HEIGHT], x: usize, y: usize) -> bool {
+    pattern[y].as_bytes().get(x) == Some(&b'#')
+}
+
+/// Reads the letters in an image, given as rows of pixels with `true` for lit ones. Letters are found
+/// every `PITCH` columns from the first one, so any blank margin around them is fine. Letters that
+/// can't be read come out as `?`. Returns `None` if the image isn't six rows tall once blank rows are
+/// trimmed.
+pub fn recognize<R: AsRef<[bool]>>(rows: &[R]) -> Option<String> {
+    let rows: Vec<&[bool]> = rows.iter()
+        .map(|row| row.as_ref())
+        .skip_while(|row| !row.contains(&true))
+        .collect();
+    let rows = &rows[..rows.iter().rposition(|row| row.contains(&true))? + 1];
+    if rows.len() != HEIGHT {
+        return None;
+    }
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let lit = |x: isize, y: usize| x >= 0 && rows[y].get(x as usize).copied().unwrap_or(false);
+    let blank = |x: usize| (0..HEIGHT).all(|y| !lit(x as isize, y));
+    let glyph_at = |x: isize| GLYPHS.iter().find(|(_, pattern)| {
+        (0..PITCH).all(|dx| (0..HEIGHT).all(|y| is_lit(pattern, dx, y) == lit(x + dx as isize, y)))
+    });
+    let first = (0..width).find(|&x| !blank(x))? as isize;
+    // letters like `I` start with a blank column, so the first one may begin before the first lit column
+    let mut x = (0..PITCH as isize).map(|lead| first - lead).find(|&x| glyph_at(x).is_some()).unwrap_or(first);
+    let mut text = String::new();
+    while (x.max(0) as usize..width).any(|x| !blank(x)) {
+        text.push(glyph_at(x).map_or('?', |&(c, _)| c));
+        x += PITCH as isize;
+    }
+    Some(text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Parses a picture drawn with `#` and `.`.
+    fn image(text: &str) -> Vec<Vec<bool>> {
+        text.lines().map(|line| line.chars().map(|c| c == '#').collect()).collect()
+    }
+
+    /// Draws `text` the way the puzzles do, one letter every `PITCH` columns.
+    fn draw(text: &str) -> Vec<Vec<bool>> {
+        let mut rows = vec![Vec::new(); HEIGHT];
+        for c in text.chars() {
+            let (_, pattern) = GLYPHS.iter().find(|&&(glyph, _)| glyph == c).unwrap();
+            for (y, row) in rows.iter_mut().enumerate() {
+                row.extend((0..PITCH).map(|x| is_lit(pattern, x, y)));
+            }
+        }
+        rows
+    }
+
+    #[test]
+    fn reads_every_glyph() {
+        for &(c, _) in GLYPHS {
+            assert_eq!(recognize(&draw(&c.to_string())), Some(c.to_string()));
+        }
+    }
+
+    #[test]
+    fn reads_y_in_the_middle_of_a_word() {
+        assert_eq!(recognize(&draw("YL")), Some("YL".to_owned()));
+        assert_eq!(recognize(&draw("BYYZ")), Some("BYYZ".to_owned()));
+        assert_eq!(recognize(&draw("IYI")), Some("IYI".to_owned()));
+    }
+
+    #[test]
+    fn ignores_margins() {
+        let mut rows = draw("HI");
+        for row in &mut rows {
+            row.splice(0..0, vec![false; 3]);
+            row.extend(vec![false; 7]);
+        }
+        rows.insert(0, vec![false; 2]);
+        rows.push(Vec::new());
+        assert_eq!(recognize(&rows), Some("HI".to_owned()));
+    }
+
+    #[test]
+    fn marks_unknown_letters() {
+        let mut rows = draw("AAA");
+        rows[0][5..9].copy_from_slice(&[true; 4]);
+        assert_eq!(recognize(&rows), Some("A?A".to_owned()));
+    }
+
+    #[test]
+    fn needs_six_rows() {
+        assert_eq!(recognize(&image("....\n....")), None);
+        assert_eq!(recognize(&draw("E")[..5]), None);
+    }
+
+    #[test]
+    fn reads_day8_image() {
+        let rows = image("\
+#....####..##....##.#...#
+#....#....#..#....#.#...#
+#....###..#.......#..#.#.
+#....#....#.##....#...#..
+#....#....#..#.#..#...#..
+####.####..###..##....#..");
+        assert_eq!(recognize(&rows), Some("LEGJY".to_owned()));
+    }
+
+    #[test]
+    fn reads_day11_image() {
+        let rows = image("\
+..##...##..###..###..#..#.####.#..#.#......
+.#..#.#..#.#..#.#..#.#.#.....#.#..#.#......
+.#....#..#.#..#.#..#.##.....#..#..#.#......
+.#.##.####.###..###..#.#...#...#..#.#......
+.#..#.#..#.#.#..#....#.#..#....#..#.#......
+..###.#..#.#..#.#....#..#.####..##..####...");
+        assert_eq!(recognize(&rows), Some("GARPKZUL".to_owned()));
+    }
+}